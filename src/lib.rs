@@ -4,18 +4,28 @@ extern crate nom;
 extern crate enum_primitive_derive;
 extern crate log;
 mod mat_error;
+mod mcos;
+#[cfg(feature = "nalgebra")]
+pub mod nalgebra;
 #[cfg(feature = "ndarray")]
 pub mod ndarray;
 mod parse;
+#[cfg(feature = "sprs")]
+pub mod sprs;
+#[cfg(test)]
+mod test_fixtures;
 mod writer;
 
 use std::io::Write;
 
 use bytes::{BufMut, BytesMut};
+use chrono::{DateTime, Duration, FixedOffset, NaiveDateTime, TimeZone, Utc};
 use nom::number::Endianness;
+use num_complex::Complex;
 use parse::Header;
 
 use crate::mat_error::MatError;
+pub use crate::parse::MatlabType;
 
 #[derive(Clone, Debug)]
 pub struct Array {
@@ -44,27 +54,1461 @@ impl Array {
         }
         count as u32
     }
-    pub fn check_index_bound(&self, index: usize, dim: usize) -> usize {
-        if index >= 0 && index < self.size[dim] {
-            index
+    /// Checks a zero-based subscript along `dim`; dimensions past `ndims` have length 1.
+    pub fn check_index_bound(&self, index: usize, dim: usize) -> Result<usize, MatError> {
+        if index < self.size.get(dim).copied().unwrap_or(1) {
+            Ok(index)
         } else {
-            dim
+            Err(self.out_of_bounds(&[index]))
         }
     }
-    pub fn set_int8(&mut self, row: usize, col: usize, value: i8) {
-        let ix0 = self.check_index_bound(row, 0);
-        let ix1 = self.check_index_bound(col, 1);
-        let index = row + col * self.size[0];
-        // println!("index={} x={} y={} value={}", index, ix0, ix1, value);
-        match &mut self.data {
-            NumericData::Int8 { real, .. } => {
-                real[index] = value;
+    pub fn set_int8(&mut self, row: usize, col: usize, value: i8) -> Result<(), MatError> {
+        self.set(&[row, col], value)
+    }
+    /// Converts zero-based subscripts to a column-major linear index like MATLAB's `sub2ind`.
+    ///
+    /// With fewer subscripts than dimensions the last subscript spans the
+    /// remaining dimensions; extra subscripts must be 0.
+    pub fn sub2ind(&self, index: &[usize]) -> Result<usize, MatError> {
+        column_major_index(&self.size, index).ok_or_else(|| self.out_of_bounds(index))
+    }
+    /// Converts a zero-based linear index to one subscript per dimension like MATLAB's `ind2sub`.
+    pub fn ind2sub(&self, index: usize) -> Result<Vec<usize>, MatError> {
+        if index >= self.get_num_elements() as usize {
+            return Err(self.out_of_bounds(&[index]));
+        }
+        let mut rest = index;
+        Ok(self
+            .size
+            .iter()
+            .map(|&len| {
+                let i = rest % len;
+                rest /= len;
+                i
+            })
+            .collect())
+    }
+    fn out_of_bounds(&self, index: &[usize]) -> MatError {
+        MatError::IndexOutOfBounds {
+            index: index.to_vec(),
+            size: self.size.clone(),
+        }
+    }
+
+    // pub fn set_imaginary_int8(&mut self, value: Vec<i8>) {}
+
+    fn from_data(name: &str, size: Vec<usize>, data: NumericData) -> Array {
+        Array {
+            array_flags: parse::ArrayFlags {
+                complex: data.is_complex(),
+                global: false,
+                logical: false,
+                class: data.class(),
+                nzmax: 0,
+            },
+            name: name.to_string(),
+            size,
+            data,
+        }
+    }
+
+    /// An array of the given shape with elements in column-major order.
+    ///
+    /// A one-dimensional shape `[n]` gives a 1xN row vector.
+    pub fn from_vec<T: MatElement>(
+        name: &str,
+        size: &[usize],
+        data: Vec<T>,
+    ) -> Result<Array, MatError> {
+        let size = check_size(size, data.len())?;
+        Ok(Array::from_data(name, size, T::into_data(data, None)))
+    }
+    /// A complex array from separate real and imaginary parts in column-major order.
+    pub fn from_complex<T: MatElement>(
+        name: &str,
+        size: &[usize],
+        real: Vec<T>,
+        imag: Vec<T>,
+    ) -> Result<Array, MatError> {
+        if real.len() != imag.len() {
+            return Err(MatError::ParamsError(format!(
+                "实部元素个数{}与虚部元素个数{}不一致",
+                real.len(),
+                imag.len()
+            )));
+        }
+        let size = check_size(size, real.len())?;
+        Ok(Array::from_data(name, size, T::into_data(real, Some(imag))))
+    }
+    /// A complex array from `Complex<T>` elements in column-major order.
+    pub fn from_complex_vec<T: MatElement>(
+        name: &str,
+        size: &[usize],
+        data: Vec<Complex<T>>,
+    ) -> Result<Array, MatError> {
+        let size = check_size(size, data.len())?;
+        let (real, imag) = data.into_iter().map(|c| (c.re, c.im)).unzip();
+        Ok(Array::from_data(name, size, T::into_data(real, Some(imag))))
+    }
+    /// A 1x1 array.
+    pub fn scalar<T: MatElement>(name: &str, value: T) -> Array {
+        Array::from_data(name, vec![1, 1], T::into_data(vec![value], None))
+    }
+    /// Converts to another numeric class with MATLAB's rules.
+    ///
+    /// Floating-point values are rounded half away from zero, out-of-range
+    /// values saturate at the limits of the integer class, NaN becomes 0 and
+    /// complex data stays complex.
+    pub fn cast(&self, class: MatlabType) -> Result<Array, MatError> {
+        let mut array = Array::from_data(&self.name, self.size.clone(), self.data.cast_to(class)?);
+        array.array_flags.global = self.array_flags.global;
+        Ok(array)
+    }
+    /// Element at an N-D subscript such as `&[row, col]`; the real part for complex arrays.
+    pub fn get<T: MatElement>(&self, index: &[usize]) -> Result<T, MatError> {
+        let linear = self.sub2ind(index)?;
+        let real = self.as_slice::<T>()?;
+        real.get(linear)
+            .copied()
+            .ok_or_else(|| self.out_of_bounds(index))
+    }
+    /// Sets the element at an N-D subscript; the real part for complex arrays.
+    pub fn set<T: MatElement>(&mut self, index: &[usize], value: T) -> Result<(), MatError> {
+        let linear = self.sub2ind(index)?;
+        let error = self.out_of_bounds(index);
+        let element = self.as_mut_slice::<T>()?.get_mut(linear).ok_or(error)?;
+        *element = value;
+        Ok(())
+    }
+    /// Element at an N-D subscript as a complex number; real arrays give a zero imaginary part.
+    pub fn get_complex<T: MatElement>(&self, index: &[usize]) -> Result<Complex<T>, MatError> {
+        let linear = self.sub2ind(index)?;
+        let (real, imag) = T::slices(&self.data).ok_or_else(|| self.class_mismatch::<T>())?;
+        let re = real.get(linear).copied();
+        let im = match imag {
+            Some(imag) => imag.get(linear).copied(),
+            None => Some(T::zero()),
+        };
+        match (re, im) {
+            (Some(re), Some(im)) => Ok(Complex::new(re, im)),
+            _ => Err(self.out_of_bounds(index)),
+        }
+    }
+    /// Sets the element at an N-D subscript, making a real array complex if needed.
+    pub fn set_complex<T: MatElement>(
+        &mut self,
+        index: &[usize],
+        value: Complex<T>,
+    ) -> Result<(), MatError> {
+        let linear = self.sub2ind(index)?;
+        let out_of_bounds = self.out_of_bounds(index);
+        let error = self.class_mismatch::<T>();
+        let (real, imag) = T::vecs_mut(&mut self.data).ok_or(error)?;
+        if linear >= real.len() || imag.as_ref().is_some_and(|imag| linear >= imag.len()) {
+            return Err(out_of_bounds);
+        }
+        //实数数组原地补上全零的虚部
+        let imag = imag.get_or_insert_with(|| vec![T::zero(); real.len()]);
+        real[linear] = value.re;
+        imag[linear] = value.im;
+        self.array_flags.complex = true;
+        Ok(())
+    }
+    /// All elements as complex numbers in column-major order.
+    pub fn iter_complex<T: MatElement>(
+        &self,
+    ) -> Result<impl Iterator<Item = Complex<T>> + '_, MatError> {
+        let (real, imag) = T::slices(&self.data).ok_or_else(|| self.class_mismatch::<T>())?;
+        Ok(real.iter().enumerate().map(move |(i, &re)| {
+            Complex::new(
+                re,
+                imag.and_then(|imag| imag.get(i).copied())
+                    .unwrap_or_else(T::zero),
+            )
+        }))
+    }
+    /// The real part in column-major order.
+    pub fn as_slice<T: MatElement>(&self) -> Result<&[T], MatError> {
+        match T::slices(&self.data) {
+            Some((real, _)) => Ok(real),
+            None => Err(self.class_mismatch::<T>()),
+        }
+    }
+    /// The real part in column-major order, mutably.
+    pub fn as_mut_slice<T: MatElement>(&mut self) -> Result<&mut [T], MatError> {
+        let error = self.class_mismatch::<T>();
+        match T::slices_mut(&mut self.data) {
+            Some((real, _)) => Ok(real),
+            None => Err(error),
+        }
+    }
+    fn class_mismatch<T: MatElement>(&self) -> MatError {
+        MatError::ClassMismatch {
+            expected: T::CLASS,
+            found: self.data.class(),
+        }
+    }
+
+    fn double(name: &str, size: Vec<usize>, real: Vec<f64>) -> Array {
+        Array::from_data(name, size, NumericData::Double { real, imag: None })
+    }
+}
+
+/// A logical array, stored by MATLAB as uint8 with the logical flag set.
+#[derive(Clone, Debug)]
+pub struct LogicalArray {
+    array_flags: parse::ArrayFlags,
+    name: String,
+    size: Vec<usize>,
+    data: Vec<bool>,
+}
+impl LogicalArray {
+    /// A logical array of the given size with values in column-major order.
+    pub fn new(name: &str, size: &[usize], data: Vec<bool>) -> Result<LogicalArray, MatError> {
+        if size.iter().product::<usize>() != data.len() {
+            return Err(MatError::ParamsError(format!(
+                "逻辑数组的维数{:?}与元素个数{}不一致",
+                size,
+                data.len()
+            )));
+        }
+        Ok(LogicalArray {
+            array_flags: LogicalArray::flags(),
+            name: name.to_string(),
+            size: size.to_vec(),
+            data,
+        })
+    }
+    /// A 1xN logical row vector.
+    pub fn from_slice(name: &str, data: &[bool]) -> LogicalArray {
+        LogicalArray {
+            array_flags: LogicalArray::flags(),
+            name: name.to_string(),
+            size: vec![1, data.len()],
+            data: data.to_vec(),
+        }
+    }
+    fn flags() -> parse::ArrayFlags {
+        parse::ArrayFlags {
+            complex: false,
+            global: false,
+            logical: true,
+            class: parse::MatlabType::UInt8,
+            nzmax: 0,
+        }
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn size(&self) -> &Vec<usize> {
+        &self.size
+    }
+    pub fn ndims(&self) -> usize {
+        self.size.len()
+    }
+    /// Values in column-major order.
+    pub fn data(&self) -> &[bool] {
+        &self.data
+    }
+    /// Value at an N-D subscript such as `&[row, col]`.
+    pub fn get_at(&self, index: &[usize]) -> Option<bool> {
+        column_major_index(&self.size, index).and_then(|index| self.data.get(index).copied())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CharArray {
+    array_flags: parse::ArrayFlags,
+    name: String,
+    size: Vec<usize>,
+    data: Vec<u16>,
+}
+impl CharArray {
+    /// A 1xN char array holding `text`.
+    pub fn new(name: &str, text: &str) -> CharArray {
+        let data: Vec<u16> = text.encode_utf16().collect();
+        CharArray {
+            array_flags: CharArray::flags(),
+            name: name.to_string(),
+            size: vec![1, data.len()],
+            data,
+        }
+    }
+    /// A char matrix with one row per string; shorter rows are padded with spaces like `char`.
+    pub fn from_rows<S: AsRef<str>>(name: &str, rows: &[S]) -> CharArray {
+        let rows: Vec<Vec<u16>> = rows
+            .iter()
+            .map(|row| row.as_ref().encode_utf16().collect())
+            .collect();
+        let cols = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        let mut data = vec![b' ' as u16; rows.len() * cols];
+        for (r, row) in rows.iter().enumerate() {
+            for (c, &unit) in row.iter().enumerate() {
+                data[r + c * rows.len()] = unit;
+            }
+        }
+        CharArray {
+            array_flags: CharArray::flags(),
+            name: name.to_string(),
+            size: vec![rows.len(), cols],
+            data,
+        }
+    }
+    fn flags() -> parse::ArrayFlags {
+        parse::ArrayFlags {
+            complex: false,
+            global: false,
+            logical: false,
+            class: parse::MatlabType::Char,
+            nzmax: 0,
+        }
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn size(&self) -> &Vec<usize> {
+        &self.size
+    }
+    pub fn ndims(&self) -> usize {
+        self.size.len()
+    }
+    /// UTF-16 code units in column-major order, as MATLAB stores them.
+    pub fn data(&self) -> &[u16] {
+        &self.data
+    }
+    /// Rows of the char matrix; N-D arrays are flattened to `size[0]` rows.
+    pub fn rows(&self) -> Vec<String> {
+        let rows = self.size.first().copied().unwrap_or(0);
+        if rows == 0 {
+            return vec![];
+        }
+        let cols = self.data.len() / rows;
+        (0..rows)
+            .map(|row| {
+                let units: Vec<u16> = (0..cols).map(|col| self.data[row + col * rows]).collect();
+                String::from_utf16_lossy(&units)
+            })
+            .collect()
+    }
+    /// The text of a 1xN (or empty) char array, `None` for multi-row matrices.
+    pub fn as_string(&self) -> Option<String> {
+        if self.size.first().copied().unwrap_or(0) <= 1 {
+            Some(String::from_utf16_lossy(&self.data))
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CellArray {
+    array_flags: parse::ArrayFlags,
+    name: String,
+    size: Vec<usize>,
+    data: Vec<MatValue>,
+}
+impl CellArray {
+    /// A cell array of the given size with elements in column-major order.
+    pub fn new(name: &str, size: &[usize], data: Vec<MatValue>) -> Result<CellArray, MatError> {
+        if size.iter().product::<usize>() != data.len() {
+            return Err(MatError::ParamsError(format!(
+                "cell的维数{:?}与元素个数{}不一致",
+                size,
+                data.len()
+            )));
+        }
+        Ok(CellArray {
+            array_flags: parse::ArrayFlags {
+                complex: false,
+                global: false,
+                logical: false,
+                class: parse::MatlabType::Cell,
+                nzmax: 0,
+            },
+            name: name.to_string(),
+            size: size.to_vec(),
+            data,
+        })
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn size(&self) -> &Vec<usize> {
+        &self.size
+    }
+    pub fn ndims(&self) -> usize {
+        self.size.len()
+    }
+    /// Cell elements in column-major order.
+    pub fn data(&self) -> &[MatValue] {
+        &self.data
+    }
+    /// Element at a column-major linear index.
+    pub fn get(&self, index: usize) -> Option<&MatValue> {
+        self.data.get(index)
+    }
+    /// Element at an N-D subscript such as `&[row, col]`.
+    pub fn get_at(&self, index: &[usize]) -> Option<&MatValue> {
+        column_major_index(&self.size, index).and_then(|index| self.data.get(index))
+    }
+}
+
+/// Collects Rust values into a cell array, a 1xN row unless a shape is given.
+#[derive(Clone, Debug, Default)]
+pub struct CellBuilder {
+    size: Option<Vec<usize>>,
+    data: Vec<MatValue>,
+}
+impl CellBuilder {
+    pub fn new() -> CellBuilder {
+        CellBuilder::default()
+    }
+    pub fn push<T: Into<MatValue>>(mut self, value: T) -> CellBuilder {
+        self.data.push(value.into());
+        self
+    }
+    /// Shape of the cell; elements are taken in column-major order.
+    pub fn shape(mut self, size: &[usize]) -> CellBuilder {
+        self.size = Some(size.to_vec());
+        self
+    }
+    pub fn build(self, name: &str) -> Result<CellArray, MatError> {
+        let size = self.size.unwrap_or_else(|| vec![1, self.data.len()]);
+        CellArray::new(name, &size, self.data)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct StructArray {
+    array_flags: parse::ArrayFlags,
+    name: String,
+    size: Vec<usize>,
+    field_names: Vec<String>,
+    data: Vec<MatValue>,
+}
+impl StructArray {
+    /// A struct array of the given size. `data` holds the field values element by
+    /// element in column-major order, each element listing its fields in `field_names` order.
+    pub fn new(
+        name: &str,
+        size: &[usize],
+        field_names: Vec<String>,
+        data: Vec<MatValue>,
+    ) -> Result<StructArray, MatError> {
+        for (i, field) in field_names.iter().enumerate() {
+            //字段名必须能用点号访问:字母开头,之后是字母、数字或下划线
+            let mut chars = field.chars();
+            let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid || field.len() > 63 {
+                return Err(MatError::ParamsError(format!("无效的字段名{:?}", field)));
+            }
+            if field_names[..i].contains(field) {
+                return Err(MatError::ParamsError(format!("重复的字段名{}", field)));
+            }
+        }
+        if size.iter().product::<usize>() * field_names.len() != data.len() {
+            return Err(MatError::ParamsError(format!(
+                "结构体的维数{:?}与字段值个数{}不一致",
+                size,
+                data.len()
+            )));
+        }
+        Ok(StructArray {
+            array_flags: parse::ArrayFlags {
+                complex: false,
+                global: false,
+                logical: false,
+                class: parse::MatlabType::Struct,
+                nzmax: 0,
+            },
+            name: name.to_string(),
+            size: size.to_vec(),
+            field_names,
+            data,
+        })
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn size(&self) -> &Vec<usize> {
+        &self.size
+    }
+    pub fn ndims(&self) -> usize {
+        self.size.len()
+    }
+    pub fn fields(&self) -> &[String] {
+        &self.field_names
+    }
+    pub fn get_num_elements(&self) -> usize {
+        self.size.iter().product()
+    }
+    /// Field value of the element at a column-major linear index.
+    pub fn get(&self, index: usize, field: &str) -> Option<&MatValue> {
+        let position = self.field_names.iter().position(|name| name == field)?;
+        if index >= self.get_num_elements() {
+            return None;
+        }
+        self.data.get(index * self.field_names.len() + position)
+    }
+    /// Field value of the element at an N-D subscript such as `&[row, col]`.
+    pub fn get_at(&self, index: &[usize], field: &str) -> Option<&MatValue> {
+        self.get(column_major_index(&self.size, index)?, field)
+    }
+}
+
+/// Collects named fields into a 1x1 struct.
+#[derive(Clone, Debug, Default)]
+pub struct StructBuilder {
+    field_names: Vec<String>,
+    data: Vec<MatValue>,
+}
+impl StructBuilder {
+    pub fn new() -> StructBuilder {
+        StructBuilder::default()
+    }
+    pub fn field<T: Into<MatValue>>(mut self, name: &str, value: T) -> StructBuilder {
+        self.field_names.push(name.to_string());
+        self.data.push(value.into());
+        self
+    }
+    pub fn build(self, name: &str) -> Result<StructArray, MatError> {
+        StructArray::new(name, &[1, 1], self.field_names, self.data)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SparseArray {
+    array_flags: parse::ArrayFlags,
+    name: String,
+    size: Vec<usize>,
+    ir: Vec<usize>,
+    jc: Vec<usize>,
+    real: Vec<f64>,
+    imag: Option<Vec<f64>>,
+}
+impl SparseArray {
+    /// A sparse matrix from compressed sparse column data: column `c` holds the
+    /// values `jc[c]..jc[c + 1]` at rows `ir`.
+    pub fn from_csc(
+        name: &str,
+        rows: usize,
+        cols: usize,
+        ir: Vec<usize>,
+        jc: Vec<usize>,
+        real: Vec<f64>,
+        imag: Option<Vec<f64>>,
+    ) -> Result<SparseArray, MatError> {
+        let nnz = jc.last().copied().unwrap_or(0);
+        if jc.len() != cols + 1 || jc[0] != 0 || jc.windows(2).any(|shift| shift[0] > shift[1]) {
+            return Err(MatError::ParamsError("稀疏矩阵的jc无效".to_string()));
+        }
+        if ir.len() != nnz || ir.iter().any(|&row| row >= rows) {
+            return Err(MatError::ParamsError("稀疏矩阵的ir无效".to_string()));
+        }
+        //MATLAB要求每列的行号严格递增
+        if jc.windows(2).any(|shift| {
+            ir[shift[0]..shift[1]]
+                .windows(2)
+                .any(|row| row[0] >= row[1])
+        }) {
+            return Err(MatError::ParamsError(
+                "稀疏矩阵每列的行号必须严格递增".to_string(),
+            ));
+        }
+        if real.len() != nnz || imag.as_ref().is_some_and(|imag| imag.len() != nnz) {
+            return Err(MatError::ParamsError(
+                "稀疏矩阵的数值个数与jc不一致".to_string(),
+            ));
+        }
+        Ok(SparseArray {
+            array_flags: parse::ArrayFlags {
+                complex: imag.is_some(),
+                global: false,
+                logical: false,
+                class: parse::MatlabType::Sparse,
+                nzmax: nnz,
+            },
+            name: name.to_string(),
+            size: vec![rows, cols],
+            ir,
+            jc,
+            real,
+            imag,
+        })
+    }
+    /// A sparse matrix from `(row, col, value)` triplets; duplicates are summed like `sparse`.
+    pub fn from_triplets(
+        name: &str,
+        rows: usize,
+        cols: usize,
+        triplets: &[(usize, usize, f64)],
+    ) -> Result<SparseArray, MatError> {
+        let triplets: Vec<_> = triplets
+            .iter()
+            .map(|&(row, col, re)| (row, col, re, 0.0))
+            .collect();
+        let (ir, jc, real, _) = SparseArray::compress(rows, cols, triplets)?;
+        SparseArray::from_csc(name, rows, cols, ir, jc, real, None)
+    }
+    /// A complex sparse matrix from `(row, col, re, im)` triplets.
+    pub fn from_complex_triplets(
+        name: &str,
+        rows: usize,
+        cols: usize,
+        triplets: &[(usize, usize, f64, f64)],
+    ) -> Result<SparseArray, MatError> {
+        let (ir, jc, real, imag) = SparseArray::compress(rows, cols, triplets.to_vec())?;
+        SparseArray::from_csc(name, rows, cols, ir, jc, real, Some(imag))
+    }
+    /// The sparsity pattern as a logical sparse matrix; explicit zeros are dropped.
+    pub fn into_logical(self) -> Result<SparseArray, MatError> {
+        if self.is_complex() {
+            return Err(MatError::ParamsError(
+                "复数稀疏矩阵不能转换为逻辑类型".to_string(),
+            ));
+        }
+        let rows = self.size.first().copied().unwrap_or(0);
+        let cols = self.size.get(1).copied().unwrap_or(1);
+        let triplets: Vec<_> = self
+            .triplets()
+            .filter(|&(_, _, value)| value != 0.0)
+            .map(|(row, col, _)| (row, col, 1.0))
+            .collect();
+        let mut logical = SparseArray::from_triplets(&self.name, rows, cols, &triplets)?;
+        logical.array_flags.logical = true;
+        Ok(logical)
+    }
+    //按列排序并合并重复的元素,合并后为0的元素不保存
+    #[allow(clippy::type_complexity)]
+    fn compress(
+        rows: usize,
+        cols: usize,
+        mut triplets: Vec<(usize, usize, f64, f64)>,
+    ) -> Result<(Vec<usize>, Vec<usize>, Vec<f64>, Vec<f64>), MatError> {
+        if triplets
+            .iter()
+            .any(|&(row, col, _, _)| row >= rows || col >= cols)
+        {
+            return Err(MatError::ParamsError("稀疏矩阵的下标越界".to_string()));
+        }
+        triplets.sort_by_key(|&(row, col, _, _)| (col, row));
+        let mut merged: Vec<(usize, usize, f64, f64)> = Vec::with_capacity(triplets.len());
+        for (row, col, re, im) in triplets {
+            match merged.last_mut() {
+                Some(last) if last.0 == row && last.1 == col => {
+                    last.2 += re;
+                    last.3 += im;
+                }
+                _ => merged.push((row, col, re, im)),
+            }
+        }
+        merged.retain(|&(_, _, re, im)| re != 0.0 || im != 0.0);
+        let mut jc = vec![0; cols + 1];
+        for &(_, col, _, _) in merged.iter() {
+            jc[col + 1] += 1;
+        }
+        for col in 0..cols {
+            jc[col + 1] += jc[col];
+        }
+        let ir = merged.iter().map(|m| m.0).collect();
+        let real = merged.iter().map(|m| m.2).collect();
+        let imag = merged.iter().map(|m| m.3).collect();
+        Ok((ir, jc, real, imag))
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn size(&self) -> &Vec<usize> {
+        &self.size
+    }
+    pub fn ndims(&self) -> usize {
+        self.size.len()
+    }
+    pub fn nzmax(&self) -> usize {
+        self.array_flags.nzmax
+    }
+    /// Number of stored values, `jc[cols]`.
+    pub fn nnz(&self) -> usize {
+        self.jc.last().copied().unwrap_or(0)
+    }
+    pub fn is_complex(&self) -> bool {
+        self.imag.is_some()
+    }
+    pub fn is_logical(&self) -> bool {
+        self.array_flags.logical
+    }
+    /// Row index of each stored value, `nnz` long like [`SparseArray::real`].
+    pub fn ir(&self) -> &[usize] {
+        &self.ir
+    }
+    /// Column `c` holds the stored values `jc[c]..jc[c + 1]`.
+    pub fn jc(&self) -> &[usize] {
+        &self.jc
+    }
+    pub fn real(&self) -> &[f64] {
+        &self.real
+    }
+    pub fn imag(&self) -> Option<&[f64]> {
+        self.imag.as_deref()
+    }
+    /// `(row, col, value)` of the stored real parts, column by column.
+    pub fn triplets(&self) -> impl Iterator<Item = (usize, usize, f64)> + '_ {
+        self.positions()
+            .map(move |(row, col, k)| (row, col, self.real[k]))
+    }
+    /// `(row, col, value)` of the stored imaginary parts, `None` for real matrices.
+    pub fn imag_triplets(&self) -> Option<impl Iterator<Item = (usize, usize, f64)> + '_> {
+        let imag = self.imag.as_ref()?;
+        Some(
+            self.positions()
+                .map(move |(row, col, k)| (row, col, imag[k])),
+        )
+    }
+    fn positions(&self) -> impl Iterator<Item = (usize, usize, usize)> + '_ {
+        self.jc
+            .windows(2)
+            .enumerate()
+            .flat_map(move |(col, shift)| (shift[0]..shift[1]).map(move |k| (self.ir[k], col, k)))
+    }
+    /// Full matrix; logical sparse matrices become logical uint8 arrays.
+    pub fn to_dense(&self) -> Array {
+        let rows = self.size.first().copied().unwrap_or(0);
+        let cols = self.size.get(1).copied().unwrap_or(1);
+        let mut real = vec![0f64; rows * cols];
+        for (row, col, value) in self.triplets() {
+            real[row + col * rows] += value;
+        }
+        let imag = self.imag_triplets().map(|triplets| {
+            let mut imag = vec![0f64; rows * cols];
+            for (row, col, value) in triplets {
+                imag[row + col * rows] += value;
+            }
+            imag
+        });
+        let data = if self.is_logical() {
+            NumericData::UInt8 {
+                real: real.into_iter().map(|x| (x != 0.0) as u8).collect(),
+                imag: None,
+            }
+        } else {
+            NumericData::Double { real, imag }
+        };
+        Array {
+            array_flags: parse::ArrayFlags {
+                complex: self.is_complex() && !self.is_logical(),
+                global: self.array_flags.global,
+                logical: self.is_logical(),
+                class: if self.is_logical() {
+                    parse::MatlabType::UInt8
+                } else {
+                    parse::MatlabType::Double
+                },
+                nzmax: 0,
+            },
+            name: self.name.clone(),
+            size: vec![rows, cols],
+            data,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ObjectArray {
+    class_name: String,
+    data: StructArray,
+}
+impl ObjectArray {
+    pub fn name(&self) -> &str {
+        self.data.name()
+    }
+    pub fn size(&self) -> &Vec<usize> {
+        self.data.size()
+    }
+    pub fn class_name(&self) -> &str {
+        &self.class_name
+    }
+    /// The object fields, laid out like a struct array.
+    pub fn as_struct(&self) -> &StructArray {
+        &self.data
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FunctionHandle {
+    name: String,
+    size: Vec<usize>,
+    data: Box<MatValue>,
+}
+impl FunctionHandle {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn size(&self) -> &Vec<usize> {
+        &self.size
+    }
+    /// The struct MATLAB stores to describe the function and its workspace.
+    pub fn data(&self) -> &MatValue {
+        &self.data
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct OpaqueArray {
+    name: String,
+    size: Vec<usize>,
+    object_type: String,
+    class_name: String,
+    data: Box<MatValue>,
+}
+impl OpaqueArray {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /// Size of the object array, read from the MCOS reference when there is one.
+    pub fn size(&self) -> &Vec<usize> {
+        &self.size
+    }
+    /// Storage scheme of the object, `MCOS` for classdef objects.
+    pub fn object_type(&self) -> &str {
+        &self.object_type
+    }
+    pub fn class_name(&self) -> &str {
+        &self.class_name
+    }
+    /// The raw value stored inline with the object.
+    pub fn data(&self) -> &MatValue {
+        &self.data
+    }
+}
+
+/// A MATLAB `string` array, read from its MCOS object.
+#[derive(Clone, Debug)]
+pub struct StringArray {
+    name: String,
+    size: Vec<usize>,
+    data: Vec<Option<String>>,
+}
+impl StringArray {
+    /// A string array of the given size with elements in column-major order;
+    /// `None` stands for a missing string.
+    pub fn new(
+        name: &str,
+        size: &[usize],
+        data: Vec<Option<String>>,
+    ) -> Result<StringArray, MatError> {
+        Ok(StringArray {
+            name: name.to_string(),
+            size: check_size(size, data.len())?,
+            data,
+        })
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn size(&self) -> &Vec<usize> {
+        &self.size
+    }
+    pub fn ndims(&self) -> usize {
+        self.size.len()
+    }
+    /// Strings in column-major order.
+    pub fn data(&self) -> &[Option<String>] {
+        &self.data
+    }
+    pub fn iter(&self) -> impl Iterator<Item = Option<&str>> + '_ {
+        self.data.iter().map(|text| text.as_deref())
+    }
+    /// String at a column-major linear index, `Some(None)` if it is missing.
+    pub fn get(&self, index: usize) -> Option<Option<&str>> {
+        self.data.get(index).map(|text| text.as_deref())
+    }
+    /// String at an N-D subscript such as `&[row, col]`.
+    pub fn get_at(&self, index: &[usize]) -> Option<Option<&str>> {
+        self.get(column_major_index(&self.size, index)?)
+    }
+}
+
+/// A MATLAB `datetime` array.
+#[derive(Clone, Debug)]
+pub struct DateTimeArray {
+    name: String,
+    size: Vec<usize>,
+    data: Vec<Option<NaiveDateTime>>,
+    time_zone: Option<String>,
+    format: String,
+}
+impl DateTimeArray {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn size(&self) -> &Vec<usize> {
+        &self.size
+    }
+    pub fn ndims(&self) -> usize {
+        self.size.len()
+    }
+    /// Times in column-major order, `None` for `NaT`.
+    ///
+    /// Arrays with a time zone hold UTC times, unzoned arrays hold the wall-clock times.
+    pub fn data(&self) -> &[Option<NaiveDateTime>] {
+        &self.data
+    }
+    /// The `TimeZone` property, `None` for unzoned arrays.
+    pub fn time_zone(&self) -> Option<&str> {
+        self.time_zone.as_deref()
+    }
+    /// The MATLAB display format, empty for the default one.
+    pub fn format(&self) -> &str {
+        &self.format
+    }
+    /// Times in the array's zone, if it is `UTC` or a fixed offset such as `+02:00`.
+    ///
+    /// Returns `None` for unzoned arrays and named zones like `Europe/Berlin`.
+    pub fn to_fixed_offset(&self) -> Option<Vec<Option<DateTime<FixedOffset>>>> {
+        let offset = match self.time_zone.as_deref()? {
+            "UTC" | "Z" => FixedOffset::east_opt(0)?,
+            zone => zone.parse::<FixedOffset>().ok()?,
+        };
+        let data = self
+            .data
+            .iter()
+            .map(|time| time.map(|time| offset.from_utc_datetime(&time)))
+            .collect();
+        Some(data)
+    }
+    /// Times as UTC instants, for any zoned array including named zones.
+    ///
+    /// Returns `None` for unzoned arrays, whose times are not tied to an instant.
+    pub fn to_utc(&self) -> Option<Vec<Option<DateTime<Utc>>>> {
+        self.time_zone.as_ref()?;
+        let data = self
+            .data
+            .iter()
+            .map(|time| time.map(|time| time.and_utc()))
+            .collect();
+        Some(data)
+    }
+}
+
+/// A MATLAB `duration` array.
+#[derive(Clone, Debug)]
+pub struct DurationArray {
+    name: String,
+    size: Vec<usize>,
+    data: Vec<Option<Duration>>,
+    format: String,
+}
+impl DurationArray {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn size(&self) -> &Vec<usize> {
+        &self.size
+    }
+    pub fn ndims(&self) -> usize {
+        self.size.len()
+    }
+    /// Durations in column-major order, `None` for `NaN` and infinite values.
+    pub fn data(&self) -> &[Option<Duration>] {
+        &self.data
+    }
+    /// The MATLAB display format, such as `hh:mm:ss`.
+    pub fn format(&self) -> &str {
+        &self.format
+    }
+}
+
+/// A MATLAB `table` or `timetable`, stored column by column.
+#[derive(Clone, Debug)]
+pub struct Table {
+    name: String,
+    size: Vec<usize>,
+    variable_names: Vec<String>,
+    columns: Vec<MatValue>,
+    num_rows: usize,
+    row_names: Vec<String>,
+    row_times: Option<Box<MatValue>>,
+    timetable: bool,
+}
+impl Table {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /// `[rows, variables]`, like `size` in MATLAB.
+    pub fn size(&self) -> &Vec<usize> {
+        &self.size
+    }
+    pub fn num_rows(&self) -> usize {
+        self.num_rows
+    }
+    pub fn num_columns(&self) -> usize {
+        self.columns.len()
+    }
+    pub fn variable_names(&self) -> &[String] {
+        &self.variable_names
+    }
+    /// Column variables in table order; each has one row per table row.
+    pub fn columns(&self) -> &[MatValue] {
+        &self.columns
+    }
+    pub fn column(&self, name: &str) -> Option<&MatValue> {
+        let position = self.variable_names.iter().position(|n| n == name)?;
+        self.columns.get(position)
+    }
+    /// Row names of a table, empty if it has none.
+    pub fn row_names(&self) -> &[String] {
+        &self.row_names
+    }
+    /// Row times of a timetable, a datetime or duration array.
+    ///
+    /// Regularly sampled timetables get their times expanded from the start and step;
+    /// `None` for tables and for row times this crate cannot read.
+    pub fn row_times(&self) -> Option<&MatValue> {
+        self.row_times.as_deref()
+    }
+    pub fn is_timetable(&self) -> bool {
+        self.timetable
+    }
+}
+
+/// A MATLAB `categorical` array.
+#[derive(Clone, Debug)]
+pub struct Categorical {
+    name: String,
+    size: Vec<usize>,
+    codes: Vec<u32>,
+    categories: Vec<String>,
+    ordinal: bool,
+}
+impl Categorical {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn size(&self) -> &Vec<usize> {
+        &self.size
+    }
+    pub fn ndims(&self) -> usize {
+        self.size.len()
+    }
+    /// 1-based indices into the categories in column-major order, 0 for `<undefined>`.
+    pub fn codes(&self) -> &[u32] {
+        &self.codes
+    }
+    pub fn categories(&self) -> &[String] {
+        &self.categories
+    }
+    pub fn is_ordinal(&self) -> bool {
+        self.ordinal
+    }
+    /// Category labels in column-major order, `None` for `<undefined>`.
+    pub fn iter(&self) -> impl Iterator<Item = Option<&str>> + '_ {
+        self.codes.iter().map(|&code| self.label(code))
+    }
+    /// Label at a column-major linear index, `Some(None)` if it is undefined.
+    pub fn get(&self, index: usize) -> Option<Option<&str>> {
+        self.codes.get(index).map(|&code| self.label(code))
+    }
+    fn label(&self, code: u32) -> Option<&str> {
+        let category = self.categories.get((code as usize).checked_sub(1)?)?;
+        Some(category.as_str())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum MatValue {
+    Numeric(Array),
+    Logical(LogicalArray),
+    Char(CharArray),
+    Sparse(SparseArray),
+    Cell(CellArray),
+    Struct(StructArray),
+    Object(ObjectArray),
+    FunctionHandle(FunctionHandle),
+    Opaque(OpaqueArray),
+    String(StringArray),
+    DateTime(DateTimeArray),
+    Duration(DurationArray),
+    Table(Table),
+    Categorical(Categorical),
+    /// An element this crate cannot read yet, kept so that cells and structs keep their shape.
+    Unsupported,
+}
+impl MatValue {
+    pub fn name(&self) -> &str {
+        match self {
+            MatValue::Numeric(array) => array.name(),
+            MatValue::Logical(array) => array.name(),
+            MatValue::Char(array) => array.name(),
+            MatValue::Sparse(array) => array.name(),
+            MatValue::Cell(array) => array.name(),
+            MatValue::Struct(array) => array.name(),
+            MatValue::Object(array) => array.name(),
+            MatValue::FunctionHandle(array) => array.name(),
+            MatValue::Opaque(array) => array.name(),
+            MatValue::String(array) => array.name(),
+            MatValue::DateTime(array) => array.name(),
+            MatValue::Duration(array) => array.name(),
+            MatValue::Table(table) => table.name(),
+            MatValue::Categorical(array) => array.name(),
+            MatValue::Unsupported => "",
+        }
+    }
+    pub fn size(&self) -> &Vec<usize> {
+        //不支持的元素没有维数
+        static NO_SIZE: Vec<usize> = Vec::new();
+        match self {
+            MatValue::Numeric(array) => array.size(),
+            MatValue::Logical(array) => array.size(),
+            MatValue::Char(array) => array.size(),
+            MatValue::Sparse(array) => array.size(),
+            MatValue::Cell(array) => array.size(),
+            MatValue::Struct(array) => array.size(),
+            MatValue::Object(array) => array.size(),
+            MatValue::FunctionHandle(array) => array.size(),
+            MatValue::Opaque(array) => array.size(),
+            MatValue::String(array) => array.size(),
+            MatValue::DateTime(array) => array.size(),
+            MatValue::Duration(array) => array.size(),
+            MatValue::Table(table) => table.size(),
+            MatValue::Categorical(array) => array.size(),
+            MatValue::Unsupported => &NO_SIZE,
+        }
+    }
+    pub fn as_numeric(&self) -> Option<&Array> {
+        match self {
+            MatValue::Numeric(array) => Some(array),
+            _ => None,
+        }
+    }
+    pub fn as_logical(&self) -> Option<&LogicalArray> {
+        match self {
+            MatValue::Logical(array) => Some(array),
+            _ => None,
+        }
+    }
+    pub fn as_char(&self) -> Option<&CharArray> {
+        match self {
+            MatValue::Char(array) => Some(array),
+            _ => None,
+        }
+    }
+    pub fn as_sparse(&self) -> Option<&SparseArray> {
+        match self {
+            MatValue::Sparse(array) => Some(array),
+            _ => None,
+        }
+    }
+    pub fn as_cell(&self) -> Option<&CellArray> {
+        match self {
+            MatValue::Cell(array) => Some(array),
+            _ => None,
+        }
+    }
+    pub fn as_struct(&self) -> Option<&StructArray> {
+        match self {
+            MatValue::Struct(array) => Some(array),
+            _ => None,
+        }
+    }
+    pub fn as_object(&self) -> Option<&ObjectArray> {
+        match self {
+            MatValue::Object(array) => Some(array),
+            _ => None,
+        }
+    }
+    pub fn as_function_handle(&self) -> Option<&FunctionHandle> {
+        match self {
+            MatValue::FunctionHandle(array) => Some(array),
+            _ => None,
+        }
+    }
+    pub fn as_opaque(&self) -> Option<&OpaqueArray> {
+        match self {
+            MatValue::Opaque(array) => Some(array),
+            _ => None,
+        }
+    }
+    pub fn as_string_array(&self) -> Option<&StringArray> {
+        match self {
+            MatValue::String(array) => Some(array),
+            _ => None,
+        }
+    }
+    pub fn as_datetime(&self) -> Option<&DateTimeArray> {
+        match self {
+            MatValue::DateTime(array) => Some(array),
+            _ => None,
+        }
+    }
+    pub fn as_duration(&self) -> Option<&DurationArray> {
+        match self {
+            MatValue::Duration(array) => Some(array),
+            _ => None,
+        }
+    }
+    pub fn as_table(&self) -> Option<&Table> {
+        match self {
+            MatValue::Table(table) => Some(table),
+            _ => None,
+        }
+    }
+    pub fn as_categorical(&self) -> Option<&Categorical> {
+        match self {
+            MatValue::Categorical(array) => Some(array),
+            _ => None,
+        }
+    }
+    //暂不支持的数据类型转换为MatValue::Unsupported
+    fn try_from(data_element: parse::DataElement) -> Result<Self, MatError> {
+        let value = match data_element {
+            parse::DataElement::NumericMatrix(flags, dims, name, real, _) if flags.logical => {
+                MatValue::Logical(LogicalArray {
+                    array_flags: flags,
+                    size: dims.into_iter().map(|d| d as usize).collect(),
+                    name,
+                    data: NumericData::try_from(flags.class, real, None)?.to_bools(),
+                })
+            }
+            parse::DataElement::NumericMatrix(flags, dims, name, real, imag) => {
+                let data = NumericData::try_from(flags.class, real, imag)?;
+                let size: Vec<usize> = dims.into_iter().map(|d| d as usize).collect();
+                //元素个数必须与维数相符,虚部与实部等长,否则后续按下标访问会越界
+                let (len, imag_len) = data.lens();
+                let size = check_size(&size, len)?;
+                if imag_len.is_some_and(|imag_len| imag_len != len) {
+                    return Err(MatError::ConversionError);
+                }
+                MatValue::Numeric(Array {
+                    array_flags: flags,
+                    size,
+                    name,
+                    data,
+                })
+            }
+            parse::DataElement::CharMatrix(flags, dims, name, data) => MatValue::Char(CharArray {
+                array_flags: flags,
+                size: dims.into_iter().map(|d| d as usize).collect(),
+                name,
+                data,
+            }),
+            parse::DataElement::CellMatrix(flags, dims, name, cells) => {
+                let data = MatValue::try_from_all(cells)?;
+                MatValue::Cell(CellArray {
+                    array_flags: flags,
+                    size: dims.into_iter().map(|d| d as usize).collect(),
+                    name,
+                    data,
+                })
+            }
+            parse::DataElement::StructMatrix(flags, dims, name, field_names, fields) => {
+                let data = MatValue::try_from_all(fields)?;
+                MatValue::Struct(StructArray {
+                    array_flags: flags,
+                    size: dims.into_iter().map(|d| d as usize).collect(),
+                    name,
+                    field_names,
+                    data,
+                })
+            }
+            parse::DataElement::ObjectMatrix(
+                flags,
+                dims,
+                name,
+                class_name,
+                field_names,
+                fields,
+            ) => {
+                let data = MatValue::try_from_all(fields)?;
+                MatValue::Object(ObjectArray {
+                    class_name,
+                    data: StructArray {
+                        array_flags: flags,
+                        size: dims.into_iter().map(|d| d as usize).collect(),
+                        name,
+                        field_names,
+                        data,
+                    },
+                })
+            }
+            parse::DataElement::FunctionMatrix(dims, name, data) => {
+                let data = MatValue::try_from(*data)?;
+                MatValue::FunctionHandle(FunctionHandle {
+                    size: dims.into_iter().map(|d| d as usize).collect(),
+                    name,
+                    data: Box::new(data),
+                })
+            }
+            parse::DataElement::OpaqueMatrix(name, object_type, class_name, data) => {
+                let data = MatValue::try_from(*data)?;
+                let size = match &data {
+                    MatValue::Numeric(reference) => mcos::reference_size(reference),
+                    _ => None,
+                };
+                MatValue::Opaque(OpaqueArray {
+                    name,
+                    size: size.unwrap_or_else(|| vec![1, 1]),
+                    object_type,
+                    class_name,
+                    data: Box::new(data),
+                })
+            }
+            parse::DataElement::SparseMatrix(flags, dims, name, mut ir, jc, real, imag) => {
+                let data = NumericData::try_from(parse::MatlabType::Double, real, imag)?;
+                let (real, mut imag) = f64::into_vecs(data).map_err(|_| MatError::InternalError)?;
+                let nnz = jc.last().copied().unwrap_or(0);
+                //逻辑稀疏矩阵可能不存储数值
+                let mut real = if flags.logical && real.is_empty() {
+                    vec![1f64; nnz]
+                } else {
+                    real
+                };
+                let (rows, cols) = match dims.as_slice() {
+                    [rows, cols] => match (usize::try_from(*rows), usize::try_from(*cols)) {
+                        (Ok(rows), Ok(cols)) => (rows, cols),
+                        _ => return Err(MatError::ConversionError),
+                    },
+                    _ => return Err(MatError::ConversionError),
+                };
+                if ir.len() < nnz
+                    || real.len() < nnz
+                    || imag.as_ref().is_some_and(|imag| imag.len() < nnz)
+                {
+                    return Err(MatError::ConversionError);
+                }
+                //文件中ir和数值按nzmax分配,只保留前nnz个
+                ir.truncate(nnz);
+                real.truncate(nnz);
+                if let Some(imag) = imag.as_mut() {
+                    imag.truncate(nnz);
+                }
+                //由from_csc检查jc和ir,损坏的文件返回错误而不是在取值时panic
+                let mut sparse = SparseArray::from_csc(&name, rows, cols, ir, jc, real, imag)
+                    .map_err(|_| MatError::ConversionError)?;
+                sparse.array_flags = flags;
+                MatValue::Sparse(sparse)
             }
-            _ => {}
+            parse::DataElement::Unsupported => MatValue::Unsupported,
+        };
+        Ok(value)
+    }
+    fn try_from_all(data_elements: Vec<parse::DataElement>) -> Result<Vec<Self>, MatError> {
+        data_elements.into_iter().map(MatValue::try_from).collect()
+    }
+}
+impl From<Array> for MatValue {
+    fn from(array: Array) -> Self {
+        if array.array_flags.logical {
+            MatValue::Logical(LogicalArray {
+                array_flags: LogicalArray::flags(),
+                data: array.data.to_bools(),
+                name: array.name,
+                size: array.size,
+            })
+        } else {
+            MatValue::Numeric(array)
+        }
+    }
+}
+impl From<LogicalArray> for MatValue {
+    fn from(array: LogicalArray) -> Self {
+        MatValue::Logical(array)
+    }
+}
+impl From<bool> for MatValue {
+    fn from(value: bool) -> Self {
+        MatValue::Logical(LogicalArray::from_slice("", &[value]))
+    }
+}
+impl From<Vec<bool>> for MatValue {
+    fn from(values: Vec<bool>) -> Self {
+        MatValue::Logical(LogicalArray::from_slice("", &values))
+    }
+}
+impl From<f64> for MatValue {
+    fn from(value: f64) -> Self {
+        MatValue::Numeric(Array::double("", vec![1, 1], vec![value]))
+    }
+}
+impl From<Vec<f64>> for MatValue {
+    fn from(values: Vec<f64>) -> Self {
+        MatValue::Numeric(Array::double("", vec![1, values.len()], values))
+    }
+}
+impl From<&str> for MatValue {
+    fn from(text: &str) -> Self {
+        MatValue::Char(CharArray::new("", text))
+    }
+}
+impl From<String> for MatValue {
+    fn from(text: String) -> Self {
+        MatValue::Char(CharArray::new("", &text))
+    }
+}
+impl From<StringArray> for MatValue {
+    fn from(array: StringArray) -> Self {
+        MatValue::String(array)
+    }
+}
+impl From<CharArray> for MatValue {
+    fn from(array: CharArray) -> Self {
+        MatValue::Char(array)
+    }
+}
+impl From<SparseArray> for MatValue {
+    fn from(array: SparseArray) -> Self {
+        MatValue::Sparse(array)
+    }
+}
+impl From<CellArray> for MatValue {
+    fn from(array: CellArray) -> Self {
+        MatValue::Cell(array)
+    }
+}
+impl From<StructArray> for MatValue {
+    fn from(array: StructArray) -> Self {
+        MatValue::Struct(array)
+    }
+}
+
+//MATLAB中的数组至少有两个维度
+fn matlab_size(size: &[usize]) -> Vec<usize> {
+    match size {
+        [] => vec![1, 1],
+        [n] => vec![1, *n],
+        _ => size.to_vec(),
+    }
+}
+
+fn check_size(size: &[usize], len: usize) -> Result<Vec<usize>, MatError> {
+    if size.is_empty() || size.iter().product::<usize>() != len {
+        return Err(MatError::ParamsError(format!(
+            "矩阵的维数{:?}与元素个数{}不一致",
+            size, len
+        )));
+    }
+    Ok(matlab_size(size))
+}
+
+//按列优先计算N维下标对应的线性下标,越界时返回None。
+//下标个数少于维数时最后一个下标覆盖剩余维度,多出的下标只能为0,与MATLAB一致
+fn column_major_index(size: &[usize], index: &[usize]) -> Option<usize> {
+    let (last, leading) = index.split_last()?;
+    let mut linear = 0;
+    let mut stride = 1;
+    for (dim, &i) in leading.iter().enumerate() {
+        let len = size.get(dim).copied().unwrap_or(1);
+        if i >= len {
+            return None;
         }
+        linear += i * stride;
+        stride *= len;
+    }
+    let len: usize = size.iter().skip(leading.len()).product();
+    if *last >= len {
+        return None;
     }
-
-    // pub fn set_imaginary_int8(&mut self, value: Vec<i8>) {}
+    Some(linear + last * stride)
 }
 
 #[derive(Clone, Debug)]
@@ -114,90 +1558,33 @@ impl NumericData {
     fn to_numberic_bytes(&self, endianness: nom::number::Endianness) -> (BytesMut, BytesMut) {
         let mut real_bytes = BytesMut::new();
         let mut imag_bytes = BytesMut::new();
-        match self {
-            NumericData::Int8 { real, imag } => {
-                for v in real {
-                    real_bytes.put_i8(v.to_owned());
-                }
-            }
-            NumericData::UInt8 { real, imag } => {
-                for v in real {
-                    real_bytes.put_u8(v.to_owned());
-                }
-            }
-            NumericData::Int16 { real, imag } => {
-                for v in real {
-                    if endianness == nom::number::Endianness::Big {
-                        real_bytes.put_i16(v.to_owned());
-                    } else {
-                        real_bytes.put_i16_le(v.to_owned());
-                    }
-                }
-            }
-            NumericData::UInt16 { real, imag } => {
-                for v in real {
-                    if endianness == nom::number::Endianness::Big {
-                        real_bytes.put_u16(v.to_owned());
-                    } else {
-                        real_bytes.put_u16_le(v.to_owned());
-                    }
-                }
-            }
-            NumericData::Int32 { real, imag } => {
-                for v in real {
-                    if endianness == nom::number::Endianness::Big {
-                        real_bytes.put_i32(v.to_owned());
-                    } else {
-                        real_bytes.put_i32_le(v.to_owned());
-                    }
-                }
-            }
-            NumericData::UInt32 { real, imag } => {
-                for v in real {
-                    if endianness == nom::number::Endianness::Big {
-                        real_bytes.put_u32(v.to_owned());
-                    } else {
-                        real_bytes.put_u32_le(v.to_owned());
-                    }
-                }
-            }
-            NumericData::Int64 { real, imag } => {
-                for v in real {
-                    if endianness == nom::number::Endianness::Big {
-                        real_bytes.put_i64(v.to_owned());
-                    } else {
-                        real_bytes.put_i64_le(v.to_owned());
-                    }
-                }
-            }
-            NumericData::UInt64 { real, imag } => {
-                for v in real {
-                    if endianness == nom::number::Endianness::Big {
-                        real_bytes.put_u64(v.to_owned());
-                    } else {
-                        real_bytes.put_u64_le(v.to_owned());
+        macro_rules! put_values {
+            ($real:expr, $imag:expr, $put:ident, $put_le:ident) => {
+                for (bytes, values) in [
+                    (&mut real_bytes, Some($real)),
+                    (&mut imag_bytes, $imag.as_ref()),
+                ] {
+                    for v in values.into_iter().flatten() {
+                        if endianness == nom::number::Endianness::Big {
+                            bytes.$put(v.to_owned());
+                        } else {
+                            bytes.$put_le(v.to_owned());
+                        }
                     }
                 }
-            }
-            NumericData::Single { real, imag } => {
-                for v in real {
-                    if endianness == nom::number::Endianness::Big {
-                        real_bytes.put_f32(v.to_owned());
-                    } else {
-                        real_bytes.put_f32_le(v.to_owned());
-                    }
-                }
-            }
-            NumericData::Double { real, imag } => {
-                for v in real {
-                    if endianness == nom::number::Endianness::Big {
-                        real_bytes.put_f64(v.to_owned());
-                    } else {
-                        real_bytes.put_f64_le(v.to_owned());
-                    }
-                }
-            }
-            _ => {}
+            };
+        }
+        match self {
+            NumericData::Int8 { real, imag } => put_values!(real, imag, put_i8, put_i8),
+            NumericData::UInt8 { real, imag } => put_values!(real, imag, put_u8, put_u8),
+            NumericData::Int16 { real, imag } => put_values!(real, imag, put_i16, put_i16_le),
+            NumericData::UInt16 { real, imag } => put_values!(real, imag, put_u16, put_u16_le),
+            NumericData::Int32 { real, imag } => put_values!(real, imag, put_i32, put_i32_le),
+            NumericData::UInt32 { real, imag } => put_values!(real, imag, put_u32, put_u32_le),
+            NumericData::Int64 { real, imag } => put_values!(real, imag, put_i64, put_i64_le),
+            NumericData::UInt64 { real, imag } => put_values!(real, imag, put_u64, put_u64_le),
+            NumericData::Single { real, imag } => put_values!(real, imag, put_f32, put_f32_le),
+            NumericData::Double { real, imag } => put_values!(real, imag, put_f64, put_f64_le),
         };
         (real_bytes, imag_bytes)
     }
@@ -244,6 +1631,49 @@ impl NumericData {
             _ => (0usize, 0usize),
         }
     }
+    //逻辑数组中非0即为true
+    fn to_bools(&self) -> Vec<bool> {
+        match self {
+            NumericData::Int8 { real, .. } => real.iter().map(|&x| x != 0).collect(),
+            NumericData::UInt8 { real, .. } => real.iter().map(|&x| x != 0).collect(),
+            NumericData::Int16 { real, .. } => real.iter().map(|&x| x != 0).collect(),
+            NumericData::UInt16 { real, .. } => real.iter().map(|&x| x != 0).collect(),
+            NumericData::Int32 { real, .. } => real.iter().map(|&x| x != 0).collect(),
+            NumericData::UInt32 { real, .. } => real.iter().map(|&x| x != 0).collect(),
+            NumericData::Int64 { real, .. } => real.iter().map(|&x| x != 0).collect(),
+            NumericData::UInt64 { real, .. } => real.iter().map(|&x| x != 0).collect(),
+            NumericData::Single { real, .. } => real.iter().map(|&x| x != 0.0).collect(),
+            NumericData::Double { real, .. } => real.iter().map(|&x| x != 0.0).collect(),
+        }
+    }
+    fn class(&self) -> parse::MatlabType {
+        match self {
+            NumericData::Int8 { .. } => parse::MatlabType::Int8,
+            NumericData::UInt8 { .. } => parse::MatlabType::UInt8,
+            NumericData::Int16 { .. } => parse::MatlabType::Int16,
+            NumericData::UInt16 { .. } => parse::MatlabType::UInt16,
+            NumericData::Int32 { .. } => parse::MatlabType::Int32,
+            NumericData::UInt32 { .. } => parse::MatlabType::UInt32,
+            NumericData::Int64 { .. } => parse::MatlabType::Int64,
+            NumericData::UInt64 { .. } => parse::MatlabType::UInt64,
+            NumericData::Single { .. } => parse::MatlabType::Single,
+            NumericData::Double { .. } => parse::MatlabType::Double,
+        }
+    }
+    fn is_complex(&self) -> bool {
+        match self {
+            NumericData::Int8 { imag, .. } => imag.is_some(),
+            NumericData::UInt8 { imag, .. } => imag.is_some(),
+            NumericData::Int16 { imag, .. } => imag.is_some(),
+            NumericData::UInt16 { imag, .. } => imag.is_some(),
+            NumericData::Int32 { imag, .. } => imag.is_some(),
+            NumericData::UInt32 { imag, .. } => imag.is_some(),
+            NumericData::Int64 { imag, .. } => imag.is_some(),
+            NumericData::UInt64 { imag, .. } => imag.is_some(),
+            NumericData::Single { imag, .. } => imag.is_some(),
+            NumericData::Double { imag, .. } => imag.is_some(),
+        }
+    }
     fn to_real_size(&self) -> usize {
         match self {
             NumericData::Int8 { real, imag } => real.len(),
@@ -261,261 +1691,260 @@ impl NumericData {
     }
 }
 
+//按MATLAB的规则将存储类型转换为目标类,类型相同时不复制数据
 fn try_convert_number_format(
     target_type: parse::MatlabType,
     data: parse::NumericData,
-) -> Result<parse::NumericData, MatError> {
-    match target_type {
-        parse::MatlabType::Double => match data {
-            parse::NumericData::UInt8(data) => Ok(parse::NumericData::Double(
-                data.into_iter().map(|x| x as f64).collect(),
-            )),
-            parse::NumericData::Int16(data) => Ok(parse::NumericData::Double(
-                data.into_iter().map(|x| x as f64).collect(),
-            )),
-            parse::NumericData::UInt16(data) => Ok(parse::NumericData::Double(
-                data.into_iter().map(|x| x as f64).collect(),
-            )),
-            parse::NumericData::Int32(data) => Ok(parse::NumericData::Double(
-                data.into_iter().map(|x| x as f64).collect(),
-            )),
-            parse::NumericData::Double(data) => Ok(parse::NumericData::Double(data)),
-            _ => Err(MatError::ConversionError),
-        },
-        parse::MatlabType::Single => match data {
-            parse::NumericData::UInt8(data) => Ok(parse::NumericData::Single(
-                data.into_iter().map(|x| x as f32).collect(),
-            )),
-            parse::NumericData::Int16(data) => Ok(parse::NumericData::Single(
-                data.into_iter().map(|x| x as f32).collect(),
-            )),
-            parse::NumericData::UInt16(data) => Ok(parse::NumericData::Single(
-                data.into_iter().map(|x| x as f32).collect(),
-            )),
-            parse::NumericData::Int32(data) => Ok(parse::NumericData::Single(
-                data.into_iter().map(|x| x as f32).collect(),
-            )),
-            parse::NumericData::Single(data) => Ok(parse::NumericData::Single(data)),
-            _ => Err(MatError::ConversionError),
-        },
-        parse::MatlabType::UInt64 => match data {
-            parse::NumericData::UInt8(data) => Ok(parse::NumericData::UInt64(
-                data.into_iter().map(|x| x as u64).collect(),
-            )),
-            parse::NumericData::Int16(data) => Ok(parse::NumericData::UInt64(
-                data.into_iter().map(|x| x as u64).collect(),
-            )),
-            parse::NumericData::UInt16(data) => Ok(parse::NumericData::UInt64(
-                data.into_iter().map(|x| x as u64).collect(),
-            )),
-            parse::NumericData::Int32(data) => Ok(parse::NumericData::UInt64(
-                data.into_iter().map(|x| x as u64).collect(),
-            )),
-            parse::NumericData::UInt64(data) => Ok(parse::NumericData::UInt64(data)),
-            _ => Err(MatError::ConversionError),
-        },
-        parse::MatlabType::Int64 => match data {
-            parse::NumericData::UInt8(data) => Ok(parse::NumericData::Int64(
-                data.into_iter().map(|x| x as i64).collect(),
-            )),
-            parse::NumericData::Int16(data) => Ok(parse::NumericData::Int64(
-                data.into_iter().map(|x| x as i64).collect(),
-            )),
-            parse::NumericData::UInt16(data) => Ok(parse::NumericData::Int64(
-                data.into_iter().map(|x| x as i64).collect(),
-            )),
-            parse::NumericData::Int32(data) => Ok(parse::NumericData::Int64(
-                data.into_iter().map(|x| x as i64).collect(),
-            )),
-            parse::NumericData::Int64(data) => Ok(parse::NumericData::Int64(data)),
-            _ => Err(MatError::ConversionError),
-        },
-        parse::MatlabType::UInt32 => match data {
-            parse::NumericData::UInt8(data) => Ok(parse::NumericData::UInt32(
-                data.into_iter().map(|x| x as u32).collect(),
-            )),
-            parse::NumericData::Int16(data) => Ok(parse::NumericData::UInt32(
-                data.into_iter().map(|x| x as u32).collect(),
-            )),
-            parse::NumericData::UInt16(data) => Ok(parse::NumericData::UInt32(
-                data.into_iter().map(|x| x as u32).collect(),
-            )),
-            parse::NumericData::UInt32(data) => Ok(parse::NumericData::UInt32(data)),
-            _ => Err(MatError::ConversionError),
-        },
-        parse::MatlabType::Int32 => match data {
-            parse::NumericData::UInt8(data) => Ok(parse::NumericData::Int32(
-                data.into_iter().map(|x| x as i32).collect(),
-            )),
-            parse::NumericData::Int16(data) => Ok(parse::NumericData::Int32(
-                data.into_iter().map(|x| x as i32).collect(),
-            )),
-            parse::NumericData::UInt16(data) => Ok(parse::NumericData::Int32(
-                data.into_iter().map(|x| x as i32).collect(),
-            )),
-            parse::NumericData::Int32(data) => Ok(parse::NumericData::Int32(data)),
-            _ => Err(MatError::ConversionError),
-        },
-        parse::MatlabType::UInt16 => match data {
-            parse::NumericData::UInt8(data) => Ok(parse::NumericData::UInt16(
-                data.into_iter().map(|x| x as u16).collect(),
-            )),
-            parse::NumericData::UInt16(data) => Ok(parse::NumericData::UInt16(data)),
-            _ => Err(MatError::ConversionError),
-        },
-        parse::MatlabType::Int16 => match data {
-            parse::NumericData::UInt8(data) => Ok(parse::NumericData::Int16(
-                data.into_iter().map(|x| x as i16).collect(),
-            )),
-            parse::NumericData::Int16(data) => Ok(parse::NumericData::Int16(data)),
-            _ => Err(MatError::ConversionError),
-        },
-        parse::MatlabType::UInt8 => match data {
-            parse::NumericData::UInt8(data) => Ok(parse::NumericData::UInt8(data)),
-            _ => Err(MatError::ConversionError),
-        },
-        parse::MatlabType::Int8 => match data {
-            parse::NumericData::Int8(data) => Ok(parse::NumericData::Int8(data)),
-            _ => Err(MatError::ConversionError),
-        },
-        _ => Err(MatError::ConversionError),
+) -> Result<NumericData, MatError> {
+    let data = NumericData::from(data);
+    if data.class() == target_type {
+        Ok(data)
+    } else {
+        data.cast_to(target_type)
+    }
+}
+
+impl From<parse::NumericData> for NumericData {
+    fn from(data: parse::NumericData) -> Self {
+        match data {
+            parse::NumericData::Int8(real) => NumericData::Int8 { real, imag: None },
+            parse::NumericData::UInt8(real) => NumericData::UInt8 { real, imag: None },
+            parse::NumericData::Int16(real) => NumericData::Int16 { real, imag: None },
+            parse::NumericData::UInt16(real) => NumericData::UInt16 { real, imag: None },
+            parse::NumericData::Int32(real) => NumericData::Int32 { real, imag: None },
+            parse::NumericData::UInt32(real) => NumericData::UInt32 { real, imag: None },
+            parse::NumericData::Int64(real) => NumericData::Int64 { real, imag: None },
+            parse::NumericData::UInt64(real) => NumericData::UInt64 { real, imag: None },
+            parse::NumericData::Single(real) => NumericData::Single { real, imag: None },
+            parse::NumericData::Double(real) => NumericData::Double { real, imag: None },
+        }
     }
 }
 
+//按MATLAB类调用对应元素类型的泛型函数,非数值类返回None
+macro_rules! for_numeric_class {
+    ($class:expr, $f:ident($($arg:expr),*)) => {
+        match $class {
+            parse::MatlabType::Int8 => Some($f::<i8>($($arg),*)),
+            parse::MatlabType::UInt8 => Some($f::<u8>($($arg),*)),
+            parse::MatlabType::Int16 => Some($f::<i16>($($arg),*)),
+            parse::MatlabType::UInt16 => Some($f::<u16>($($arg),*)),
+            parse::MatlabType::Int32 => Some($f::<i32>($($arg),*)),
+            parse::MatlabType::UInt32 => Some($f::<u32>($($arg),*)),
+            parse::MatlabType::Int64 => Some($f::<i64>($($arg),*)),
+            parse::MatlabType::UInt64 => Some($f::<u64>($($arg),*)),
+            parse::MatlabType::Single => Some($f::<f32>($($arg),*)),
+            parse::MatlabType::Double => Some($f::<f64>($($arg),*)),
+            _ => None,
+        }
+    };
+}
+
 impl NumericData {
+    //实部和虚部各自的元素个数
+    fn lens(&self) -> (usize, Option<usize>) {
+        fn lens<T: MatElement>(data: &NumericData) -> Option<(usize, Option<usize>)> {
+            T::slices(data).map(|(real, imag)| (real.len(), imag.map(<[T]>::len)))
+        }
+        for_numeric_class!(self.class(), lens(self))
+            .flatten()
+            .unwrap_or((0, None))
+    }
     fn try_from(
         target_type: parse::MatlabType,
         real: parse::NumericData,
         imag: Option<parse::NumericData>,
     ) -> Result<Self, MatError> {
         let real = try_convert_number_format(target_type, real)?;
-        let imag = match imag {
-            Some(imag) => Some(try_convert_number_format(target_type, imag)?),
-            None => None,
-        };
-        match (real, imag) {
-            (parse::NumericData::Double(real), None) => Ok(NumericData::Double {
-                real: real,
-                imag: None,
-            }),
-            (parse::NumericData::Double(real), Some(parse::NumericData::Double(imag))) => {
-                Ok(NumericData::Double {
-                    real: real,
-                    imag: Some(imag),
-                })
+        match imag {
+            //实部和虚部可能以不同的类型存储
+            Some(imag) => {
+                let imag = try_convert_number_format(target_type, imag)?;
+                fn join<T: MatElement>(
+                    real: NumericData,
+                    imag: NumericData,
+                ) -> Option<NumericData> {
+                    match (T::into_vecs(real), T::into_vecs(imag)) {
+                        (Ok((real, None)), Ok((imag, None))) => {
+                            Some(T::into_data(real, Some(imag)))
+                        }
+                        _ => None,
+                    }
+                }
+                for_numeric_class!(target_type, join(real, imag))
+                    .flatten()
+                    .ok_or(MatError::InternalError)
             }
-            (parse::NumericData::Single(real), None) => Ok(NumericData::Single {
-                real: real,
-                imag: None,
-            }),
-            (parse::NumericData::Single(real), Some(parse::NumericData::Single(imag))) => {
-                Ok(NumericData::Single {
-                    real: real,
-                    imag: Some(imag),
-                })
+            None => Ok(real),
+        }
+    }
+    //MATLAB的类型转换规则:浮点数转整数时四舍五入(远离0),超出范围时饱和,NaN转为0
+    fn cast_to(&self, target_type: parse::MatlabType) -> Result<NumericData, MatError> {
+        fn cast<T: MatElement>(data: &NumericData) -> NumericData {
+            fn convert<S: Copy, T>(values: &[S], f: impl Fn(S) -> T) -> Vec<T> {
+                values.iter().map(|&x| f(x)).collect()
             }
-            (parse::NumericData::UInt64(real), None) => Ok(NumericData::UInt64 {
-                real: real,
-                imag: None,
-            }),
-            (parse::NumericData::UInt64(real), Some(parse::NumericData::UInt64(imag))) => {
-                Ok(NumericData::UInt64 {
-                    real: real,
-                    imag: Some(imag),
-                })
+            macro_rules! convert_parts {
+                ($real:expr, $imag:expr, $f:expr) => {
+                    T::into_data(
+                        convert($real, $f),
+                        $imag.as_ref().map(|imag| convert(imag, $f)),
+                    )
+                };
             }
-            (parse::NumericData::Int64(real), None) => Ok(NumericData::Int64 {
-                real: real,
-                imag: None,
-            }),
-            (parse::NumericData::Int64(real), Some(parse::NumericData::Int64(imag))) => {
-                Ok(NumericData::Int64 {
-                    real: real,
-                    imag: Some(imag),
-                })
+            match data {
+                NumericData::Int8 { real, imag } => {
+                    convert_parts!(real, imag, |x| T::from_i128(x as i128))
+                }
+                NumericData::UInt8 { real, imag } => {
+                    convert_parts!(real, imag, |x| T::from_i128(x as i128))
+                }
+                NumericData::Int16 { real, imag } => {
+                    convert_parts!(real, imag, |x| T::from_i128(x as i128))
+                }
+                NumericData::UInt16 { real, imag } => {
+                    convert_parts!(real, imag, |x| T::from_i128(x as i128))
+                }
+                NumericData::Int32 { real, imag } => {
+                    convert_parts!(real, imag, |x| T::from_i128(x as i128))
+                }
+                NumericData::UInt32 { real, imag } => {
+                    convert_parts!(real, imag, |x| T::from_i128(x as i128))
+                }
+                NumericData::Int64 { real, imag } => {
+                    convert_parts!(real, imag, |x| T::from_i128(x as i128))
+                }
+                NumericData::UInt64 { real, imag } => {
+                    convert_parts!(real, imag, |x| T::from_i128(x as i128))
+                }
+                NumericData::Single { real, imag } => {
+                    convert_parts!(real, imag, |x| T::from_f64(x as f64))
+                }
+                NumericData::Double { real, imag } => convert_parts!(real, imag, T::from_f64),
             }
-            (parse::NumericData::UInt32(real), None) => Ok(NumericData::UInt32 {
-                real: real,
-                imag: None,
-            }),
-            (parse::NumericData::UInt32(real), Some(parse::NumericData::UInt32(imag))) => {
-                Ok(NumericData::UInt32 {
-                    real: real,
-                    imag: Some(imag),
-                })
+        }
+        if self.class() == target_type {
+            return Ok(self.clone());
+        }
+        for_numeric_class!(target_type, cast(self)).ok_or(MatError::ConversionError)
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Rust element types that map onto one of MATLAB's numeric classes.
+///
+/// This trait is sealed and implemented for `i8`..`u64`, `f32` and `f64`.
+pub trait MatElement: Copy + num_traits::Zero + sealed::Sealed + 'static {
+    /// The MATLAB class an array of this element type is saved as.
+    const CLASS: MatlabType;
+    #[doc(hidden)]
+    fn slices(data: &NumericData) -> Option<(&[Self], Option<&[Self]>)>;
+    #[doc(hidden)]
+    fn slices_mut(data: &mut NumericData) -> Option<(&mut [Self], Option<&mut [Self]>)>;
+    #[doc(hidden)]
+    fn vecs_mut(data: &mut NumericData) -> Option<(&mut Vec<Self>, &mut Option<Vec<Self>>)>;
+    #[doc(hidden)]
+    fn into_vecs(data: NumericData) -> Result<(Vec<Self>, Option<Vec<Self>>), NumericData>;
+    #[doc(hidden)]
+    fn into_data(real: Vec<Self>, imag: Option<Vec<Self>>) -> NumericData;
+    #[doc(hidden)]
+    fn from_i128(value: i128) -> Self;
+    #[doc(hidden)]
+    fn from_f64(value: f64) -> Self;
+}
+macro_rules! impl_mat_element {
+    //整数类型:超出范围时饱和;浮点数四舍五入,as转换本身会饱和并将NaN转为0
+    ($t:ty, $variant:ident, int) => {
+        impl_mat_element!(
+            $t,
+            $variant,
+            |v: i128| v.clamp(<$t>::MIN as i128, <$t>::MAX as i128) as $t,
+            |v: f64| v.round() as $t
+        );
+    };
+    ($t:ty, $variant:ident, float) => {
+        impl_mat_element!($t, $variant, |v: i128| v as $t, |v: f64| v as $t);
+    };
+    ($t:ty, $variant:ident, $from_i128:expr, $from_f64:expr) => {
+        impl sealed::Sealed for $t {}
+        impl MatElement for $t {
+            const CLASS: MatlabType = MatlabType::$variant;
+            fn slices(data: &NumericData) -> Option<(&[Self], Option<&[Self]>)> {
+                match data {
+                    NumericData::$variant { real, imag } => Some((real, imag.as_deref())),
+                    _ => None,
+                }
             }
-            (parse::NumericData::Int32(real), None) => Ok(NumericData::Int32 {
-                real: real,
-                imag: None,
-            }),
-            (parse::NumericData::Int32(real), Some(parse::NumericData::Int32(imag))) => {
-                Ok(NumericData::Int32 {
-                    real: real,
-                    imag: Some(imag),
-                })
+            fn slices_mut(data: &mut NumericData) -> Option<(&mut [Self], Option<&mut [Self]>)> {
+                match data {
+                    NumericData::$variant { real, imag } => Some((real, imag.as_deref_mut())),
+                    _ => None,
+                }
             }
-            (parse::NumericData::UInt16(real), None) => Ok(NumericData::UInt16 {
-                real: real,
-                imag: None,
-            }),
-            (parse::NumericData::UInt16(real), Some(parse::NumericData::UInt16(imag))) => {
-                Ok(NumericData::UInt16 {
-                    real: real,
-                    imag: Some(imag),
-                })
+            fn vecs_mut(
+                data: &mut NumericData,
+            ) -> Option<(&mut Vec<Self>, &mut Option<Vec<Self>>)> {
+                match data {
+                    NumericData::$variant { real, imag } => Some((real, imag)),
+                    _ => None,
+                }
             }
-            (parse::NumericData::Int16(real), None) => Ok(NumericData::Int16 {
-                real: real,
-                imag: None,
-            }),
-            (parse::NumericData::Int16(real), Some(parse::NumericData::Int16(imag))) => {
-                Ok(NumericData::Int16 {
-                    real: real,
-                    imag: Some(imag),
-                })
+            fn into_vecs(data: NumericData) -> Result<(Vec<Self>, Option<Vec<Self>>), NumericData> {
+                match data {
+                    NumericData::$variant { real, imag } => Ok((real, imag)),
+                    data => Err(data),
+                }
             }
-            (parse::NumericData::UInt8(real), None) => Ok(NumericData::UInt8 {
-                real: real,
-                imag: None,
-            }),
-            (parse::NumericData::UInt8(real), Some(parse::NumericData::UInt8(imag))) => {
-                Ok(NumericData::UInt8 {
-                    real: real,
-                    imag: Some(imag),
-                })
+            fn into_data(real: Vec<Self>, imag: Option<Vec<Self>>) -> NumericData {
+                NumericData::$variant { real, imag }
             }
-            (parse::NumericData::Int8(real), None) => Ok(NumericData::Int8 {
-                real: real,
-                imag: None,
-            }),
-            (parse::NumericData::Int8(real), Some(parse::NumericData::Int8(imag))) => {
-                Ok(NumericData::Int8 {
-                    real: real,
-                    imag: Some(imag),
-                })
+            fn from_i128(value: i128) -> Self {
+                ($from_i128)(value)
+            }
+            fn from_f64(value: f64) -> Self {
+                ($from_f64)(value)
             }
-            _ => return Err(MatError::InternalError),
         }
-    }
+    };
 }
+impl_mat_element!(i8, Int8, int);
+impl_mat_element!(u8, UInt8, int);
+impl_mat_element!(i16, Int16, int);
+impl_mat_element!(u16, UInt16, int);
+impl_mat_element!(i32, Int32, int);
+impl_mat_element!(u32, UInt32, int);
+impl_mat_element!(i64, Int64, int);
+impl_mat_element!(u64, UInt64, int);
+impl_mat_element!(f32, Single, float);
+impl_mat_element!(f64, Double, float);
 
 #[derive(Clone, Debug)]
 pub struct MatFile {
     header: Header,
-    arrays: Vec<Array>,
+    values: Vec<MatValue>,
 }
 impl MatFile {
-    pub fn add_array(&mut self, array: Array) -> &mut MatFile {
-        self.arrays.push(array);
+    /// Adds a variable, replacing any existing variable with the same name in place.
+    pub fn add_array<T: Into<MatValue>>(&mut self, value: T) -> &mut MatFile {
+        let value = value.into();
+        match self.values.iter_mut().find(|v| v.name() == value.name()) {
+            Some(existing) => *existing = value,
+            None => self.values.push(value),
+        }
         self
     }
-    pub fn find_by_name<'a>(&'a self, name: &'_ str) -> Option<&'a Array> {
-        for array in &self.arrays {
-            if array.name == name {
-                return Some(array);
-            }
-        }
-        None
+    pub fn find_by_name<'a>(&'a self, name: &'_ str) -> Option<&'a MatValue> {
+        self.values.iter().find(|value| value.name() == name)
+    }
+    /// Variables in file order.
+    pub fn values(&self) -> &[MatValue] {
+        &self.values
+    }
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.values.iter().map(|value| value.name())
     }
 
     pub fn new_mat_file() -> MatFile {
@@ -527,7 +1956,7 @@ impl MatFile {
             *ptr
         };
         MatFile {
-            arrays: vec![],
+            values: vec![],
             header: Header {
                 version: 1,
                 mat_identifier: "MATLAB 5.0 MAT-file".to_string(),
@@ -581,29 +2010,30 @@ impl MatFile {
             .map_err(|err| MatError::IOError(err))?;
         let (_remaining, parse_result) = parse::parse_all(&buf)
             .map_err(|err| MatError::ParseError(parse::replace_err_slice(err, &[])))?;
-        let arrays: Result<Vec<Array>, MatError> = parse_result
-            .data_elements
-            .into_iter()
-            .filter_map(|data_element| match data_element {
-                parse::DataElement::NumericMatrix(flags, dims, name, real, imag) => {
-                    let size = dims.into_iter().map(|d| d as usize).collect();
-                    let numeric_data = match NumericData::try_from(flags.class, real, imag) {
-                        Ok(numeric_data) => numeric_data,
-                        Err(err) => return Some(Err(err)),
-                    };
-                    Some(Ok(Array {
-                        array_flags: flags,
-                        size: size,
-                        name: name,
-                        data: numeric_data,
-                    }))
-                }
-                _ => None,
-            })
-            .collect();
-        let arrays = arrays?;
+        let subsystem = match parse_result.subsystem {
+            //子系统只用于还原MCOS对象,解析失败时对象保留为Opaque,不影响其他变量
+            Some(element) => mcos::Subsystem::parse(element, parse_result.header.byte_order)
+                .unwrap_or_else(|err| {
+                    log::warn!("无法解析子系统数据,MCOS对象将保持为Opaque: {}", err);
+                    None
+                }),
+            None => None,
+        };
+        let mut values = vec![];
+        for data_element in parse_result.data_elements {
+            let value = match MatValue::try_from(data_element)? {
+                //顶层不支持的元素没有变量名,直接跳过
+                MatValue::Unsupported => continue,
+                value => value,
+            };
+            //把MCOS引用替换为对应的对象
+            values.push(match &subsystem {
+                Some(subsystem) => subsystem.resolve(value),
+                None => value,
+            });
+        }
         Ok(MatFile {
-            arrays: arrays,
+            values,
             header: Header {
                 version: 1,
                 mat_identifier: "".to_string(),
@@ -624,6 +2054,7 @@ impl MatFile {
     }
 }
 
+#[cfg(test)]
 mod tests {
 
     #[test]
@@ -641,7 +2072,7 @@ mod tests {
         let mut count = 1;
         for i in 0..rows {
             for j in 0..cols {
-                matrix.set_int8(i, j, count);
+                matrix.set_int8(i, j, count)?;
                 count += 1;
             }
         }
@@ -669,4 +2100,913 @@ mod tests {
         println!("y={:?}", array);
         Ok(())
     }
+    #[test]
+    fn char_array_rows() {
+        let flags = crate::parse::ArrayFlags {
+            complex: false,
+            global: false,
+            logical: false,
+            class: crate::parse::MatlabType::Char,
+            nzmax: 0,
+        };
+        let matrix = super::CharArray {
+            array_flags: flags,
+            name: "labels".to_string(),
+            size: vec![2, 3],
+            data: "adbecf".encode_utf16().collect(),
+        };
+        assert_eq!(matrix.rows(), vec!["abc".to_string(), "def".to_string()]);
+        assert_eq!(matrix.as_string(), None);
+        let row = super::CharArray {
+            array_flags: flags,
+            name: "unit".to_string(),
+            size: vec![1, 2],
+            data: "µs".encode_utf16().collect(),
+        };
+        assert_eq!(row.as_string(), Some("µs".to_string()));
+    }
+    #[test]
+    fn cell_array_elements() -> std::result::Result<(), crate::mat_error::MatError> {
+        use crate::parse::{ArrayFlags, DataElement, MatlabType, NumericData};
+        let flags = |class| ArrayFlags {
+            complex: false,
+            global: false,
+            logical: false,
+            class,
+            nzmax: 0,
+        };
+        let number = |x: u8| {
+            DataElement::NumericMatrix(
+                flags(MatlabType::Double),
+                vec![1, 1],
+                String::new(),
+                NumericData::UInt8(vec![x]),
+                None,
+            )
+        };
+        //2x2的cell: {1, 'a'; 2, {3}}
+        let inner = DataElement::CellMatrix(
+            flags(MatlabType::Cell),
+            vec![1, 1],
+            String::new(),
+            vec![number(3)],
+        );
+        let text = DataElement::CharMatrix(
+            flags(MatlabType::Char),
+            vec![1, 1],
+            String::new(),
+            vec![b'a' as u16],
+        );
+        let cell = DataElement::CellMatrix(
+            flags(MatlabType::Cell),
+            vec![2, 2],
+            "c".to_string(),
+            vec![number(1), number(2), text, inner],
+        );
+        let cell = match super::MatValue::try_from(cell)? {
+            super::MatValue::Cell(cell) => cell,
+            _ => panic!("expected a cell array"),
+        };
+        assert_eq!(cell.name(), "c");
+        match cell.get_at(&[1, 0]) {
+            Some(super::MatValue::Numeric(array)) => match array.data() {
+                super::NumericData::Double { real, .. } => assert_eq!(real, &vec![2.0]),
+                _ => panic!("expected double data"),
+            },
+            _ => panic!("expected a numeric element"),
+        }
+        match cell.get_at(&[0, 1]) {
+            Some(super::MatValue::Char(text)) => assert_eq!(text.as_string().unwrap(), "a"),
+            _ => panic!("expected a char element"),
+        }
+        match cell.get(3) {
+            Some(super::MatValue::Cell(inner)) => assert_eq!(inner.data().len(), 1),
+            _ => panic!("expected a nested cell"),
+        }
+        assert!(cell.get_at(&[2, 0]).is_none());
+        Ok(())
+    }
+    #[test]
+    fn unsupported_elements_keep_their_container(
+    ) -> std::result::Result<(), crate::mat_error::MatError> {
+        use crate::parse::{ArrayFlags, DataElement, MatlabType, NumericData};
+        let flags = |class| ArrayFlags {
+            complex: false,
+            global: false,
+            logical: false,
+            class,
+            nzmax: 0,
+        };
+        let number = DataElement::NumericMatrix(
+            flags(MatlabType::Double),
+            vec![1, 1],
+            String::new(),
+            NumericData::Double(vec![1.0]),
+            None,
+        );
+        let cell = DataElement::CellMatrix(
+            flags(MatlabType::Cell),
+            vec![2, 1],
+            "c".to_string(),
+            vec![number, DataElement::Unsupported],
+        );
+        let cell = super::MatValue::try_from(cell)?;
+        assert_eq!(cell.size(), &vec![2, 1]);
+        let cell = cell.as_cell().unwrap();
+        assert_eq!(cell.get(0).unwrap().size(), &vec![1, 1]);
+        assert!(matches!(cell.get(1), Some(super::MatValue::Unsupported)));
+
+        //MCOS对象的维数来自引用
+        let reference = DataElement::NumericMatrix(
+            flags(MatlabType::UInt32),
+            vec![7, 1],
+            String::new(),
+            NumericData::UInt32(vec![0xDD00_0000, 2, 1, 2, 1, 2, 1]),
+            None,
+        );
+        let opaque = DataElement::OpaqueMatrix(
+            "s".to_string(),
+            "MCOS".to_string(),
+            "string".to_string(),
+            Box::new(reference),
+        );
+        assert_eq!(super::MatValue::try_from(opaque)?.size(), &vec![1, 2]);
+        Ok(())
+    }
+    #[test]
+    fn struct_array_fields() -> std::result::Result<(), crate::mat_error::MatError> {
+        use crate::parse::{ArrayFlags, DataElement, MatlabType, NumericData};
+        let flags = |class| ArrayFlags {
+            complex: false,
+            global: false,
+            logical: false,
+            class,
+            nzmax: 0,
+        };
+        let number = |x: i16| {
+            DataElement::NumericMatrix(
+                flags(MatlabType::Int16),
+                vec![1, 1],
+                String::new(),
+                NumericData::Int16(vec![x]),
+                None,
+            )
+        };
+        //1x2x2的结构体数组, 每个元素只有字段trial
+        let data = DataElement::StructMatrix(
+            flags(MatlabType::Struct),
+            vec![1, 2, 2],
+            "s".to_string(),
+            vec!["trial".to_string()],
+            (1..=4).map(number).collect(),
+        );
+        let data = match super::MatValue::try_from(data)? {
+            super::MatValue::Struct(data) => data,
+            _ => panic!("expected a struct array"),
+        };
+        assert_eq!(data.fields(), &["trial".to_string()]);
+        assert_eq!(data.get_num_elements(), 4);
+        match data.get_at(&[0, 1, 1], "trial") {
+            Some(super::MatValue::Numeric(array)) => match array.data() {
+                super::NumericData::Int16 { real, .. } => assert_eq!(real, &vec![4]),
+                _ => panic!("expected int16 data"),
+            },
+            _ => panic!("expected a numeric field"),
+        }
+        assert!(data.get(0, "gain").is_none());
+        assert!(data.get(4, "trial").is_none());
+        Ok(())
+    }
+    #[test]
+    fn sparse_array_to_dense() -> std::result::Result<(), crate::mat_error::MatError> {
+        use crate::parse::{ArrayFlags, DataElement, MatlabType, NumericData};
+        //3x3: (2,0)=1+2i, (0,2)=3, (1,2)=4
+        let sparse = DataElement::SparseMatrix(
+            ArrayFlags {
+                complex: true,
+                global: false,
+                logical: false,
+                class: MatlabType::Sparse,
+                nzmax: 4,
+            },
+            vec![3, 3],
+            "k".to_string(),
+            vec![2, 0, 1, 0],
+            vec![0, 1, 1, 3],
+            NumericData::Double(vec![1.0, 3.0, 4.0]),
+            Some(NumericData::Double(vec![2.0, 0.0, 0.0])),
+        );
+        let sparse = match super::MatValue::try_from(sparse)? {
+            super::MatValue::Sparse(sparse) => sparse,
+            _ => panic!("expected a sparse array"),
+        };
+        assert_eq!(sparse.nzmax(), 4);
+        assert_eq!(sparse.nnz(), 3);
+        //ir与数值一样只保留nnz个
+        assert_eq!(sparse.ir(), &[2, 0, 1]);
+        assert_eq!(sparse.real().len(), 3);
+        assert_eq!(sparse.imag().map(|imag| imag.len()), Some(3));
+        let triplets: Vec<_> = sparse.triplets().collect();
+        assert_eq!(triplets, vec![(2, 0, 1.0), (0, 2, 3.0), (1, 2, 4.0)]);
+        let dense = sparse.to_dense();
+        match dense.data() {
+            super::NumericData::Double { real, imag } => {
+                assert_eq!(real, &vec![0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 3.0, 4.0, 0.0]);
+                assert_eq!(imag.as_ref().unwrap()[2], 2.0);
+            }
+            _ => panic!("expected double data"),
+        }
+        //jc递减、行号越界、维数不是二维的文件返回错误
+        let malformed = |dims: Vec<i32>, ir: Vec<usize>, jc: Vec<usize>| {
+            let values = NumericData::Double(vec![1.0; ir.len()]);
+            let flags = ArrayFlags {
+                complex: false,
+                global: false,
+                logical: false,
+                class: MatlabType::Sparse,
+                nzmax: ir.len(),
+            };
+            let element =
+                DataElement::SparseMatrix(flags, dims, "m".to_string(), ir, jc, values, None);
+            super::MatValue::try_from(element)
+        };
+        assert!(matches!(
+            malformed(vec![2, 2], vec![0, 1], vec![0, 2, 1]),
+            Err(crate::mat_error::MatError::ConversionError)
+        ));
+        assert!(malformed(vec![2, 2], vec![0, 5], vec![0, 1, 2]).is_err());
+        assert!(malformed(vec![2, 2], vec![0], vec![1, 1, 1]).is_err());
+        assert!(malformed(vec![2, 1, 1], vec![0], vec![0, 1]).is_err());
+        Ok(())
+    }
+    #[test]
+    fn mat_file_keeps_order() -> std::result::Result<(), crate::mat_error::MatError> {
+        let mut matfile = super::MatFile::new_mat_file();
+        let matrix =
+            |name| super::MatFile::new_matrix(name, 1, 2, false, crate::parse::MatlabType::Double);
+        matfile.add_array(matrix("b")?);
+        matfile.add_array(matrix("a")?);
+        let mut replaced =
+            super::MatFile::new_matrix("b", 3, 1, false, crate::parse::MatlabType::Int8)?;
+        replaced.set_int8(2, 0, 7)?;
+        matfile.add_array(replaced);
+        assert_eq!(matfile.names().collect::<Vec<_>>(), vec!["b", "a"]);
+        let b = matfile
+            .find_by_name("b")
+            .and_then(|v| v.as_numeric())
+            .unwrap();
+        assert_eq!(b.size(), &vec![3, 1]);
+        assert!(matfile.find_by_name("c").is_none());
+        Ok(())
+    }
+    //在内存中写出再读回,不留下临时文件
+    fn round_trip(
+        matfile: &super::MatFile,
+    ) -> std::result::Result<super::MatFile, crate::mat_error::MatError> {
+        let mut bytes = crate::writer::write_header(matfile)?.to_vec();
+        bytes.extend_from_slice(&crate::writer::write_body(matfile)?);
+        super::MatFile::parse(&bytes[..])
+    }
+    #[test]
+    fn char_array_round_trip() -> std::result::Result<(), crate::mat_error::MatError> {
+        let mut matfile = super::MatFile::new_mat_file();
+        matfile.add_array(super::CharArray::new("s", "温度 °C"));
+        matfile.add_array(super::CharArray::from_rows("rows", &["ab", "cde"]));
+        matfile.add_array(super::CharArray::new("empty", ""));
+        let matfile = round_trip(&matfile)?;
+        let s = matfile.find_by_name("s").and_then(|v| v.as_char()).unwrap();
+        assert_eq!(s.size(), &vec![1, 5]);
+        assert_eq!(s.as_string().unwrap(), "温度 °C");
+        let rows = matfile
+            .find_by_name("rows")
+            .and_then(|v| v.as_char())
+            .unwrap();
+        assert_eq!(rows.rows(), vec!["ab ".to_string(), "cde".to_string()]);
+        let empty = matfile
+            .find_by_name("empty")
+            .and_then(|v| v.as_char())
+            .unwrap();
+        assert_eq!(empty.as_string().unwrap(), "");
+        Ok(())
+    }
+    #[test]
+    fn cell_array_round_trip() -> std::result::Result<(), crate::mat_error::MatError> {
+        let spikes = super::CellBuilder::new()
+            .push(vec![0.5, 1.25, 3.0])
+            .push(Vec::<f64>::new())
+            .push(vec![2.0])
+            .push("trial 4")
+            .shape(&[2, 2])
+            .build("spikes")?;
+        let nested = super::CellBuilder::new()
+            .push(1.0)
+            .push(spikes.clone())
+            .build("nested")?;
+        let mut matfile = super::MatFile::new_mat_file();
+        matfile.add_array(spikes);
+        matfile.add_array(nested);
+        let matfile = round_trip(&matfile)?;
+        let spikes = matfile
+            .find_by_name("spikes")
+            .and_then(|v| v.as_cell())
+            .unwrap();
+        assert_eq!(spikes.size(), &vec![2, 2]);
+        let first = spikes.get_at(&[0, 0]).and_then(|v| v.as_numeric()).unwrap();
+        match first.data() {
+            super::NumericData::Double { real, .. } => assert_eq!(real, &vec![0.5, 1.25, 3.0]),
+            _ => panic!("expected double data"),
+        }
+        let empty = spikes.get_at(&[1, 0]).and_then(|v| v.as_numeric()).unwrap();
+        assert_eq!(empty.size(), &vec![1, 0]);
+        let label = spikes.get_at(&[1, 1]).and_then(|v| v.as_char()).unwrap();
+        assert_eq!(label.as_string().unwrap(), "trial 4");
+        let nested = matfile
+            .find_by_name("nested")
+            .and_then(|v| v.as_cell())
+            .unwrap();
+        let inner = nested.get(1).and_then(|v| v.as_cell()).unwrap();
+        assert_eq!(inner.name(), "");
+        assert_eq!(inner.data().len(), 4);
+        Ok(())
+    }
+    #[test]
+    fn struct_array_round_trip() -> std::result::Result<(), crate::mat_error::MatError> {
+        let filter = super::StructBuilder::new()
+            .field("order", 4.0)
+            .field("type", "butter")
+            .build("")?;
+        let cfg = super::StructBuilder::new()
+            .field("gain", vec![1.5, 2.5])
+            .field("filter", filter)
+            .field(
+                "channels",
+                super::CellBuilder::new().push("C3").push("C4").build("")?,
+            )
+            .build("cfg")?;
+        //1x2x2的结构体数组
+        let trials = super::StructArray::new(
+            "trials",
+            &[1, 2, 2],
+            vec!["id".to_string()],
+            (1..=4).map(|id| super::MatValue::from(id as f64)).collect(),
+        )?;
+        let mut matfile = super::MatFile::new_mat_file();
+        matfile.add_array(cfg);
+        matfile.add_array(trials);
+        let matfile = round_trip(&matfile)?;
+        let cfg = matfile
+            .find_by_name("cfg")
+            .and_then(|v| v.as_struct())
+            .unwrap();
+        assert_eq!(cfg.fields(), &["gain", "filter", "channels"]);
+        let filter = cfg.get(0, "filter").and_then(|v| v.as_struct()).unwrap();
+        let kind = filter.get(0, "type").and_then(|v| v.as_char()).unwrap();
+        assert_eq!(kind.as_string().unwrap(), "butter");
+        let channels = cfg.get(0, "channels").and_then(|v| v.as_cell()).unwrap();
+        assert_eq!(channels.data().len(), 2);
+        let trials = matfile
+            .find_by_name("trials")
+            .and_then(|v| v.as_struct())
+            .unwrap();
+        assert_eq!(trials.size(), &vec![1, 2, 2]);
+        let id = trials
+            .get_at(&[0, 0, 1], "id")
+            .and_then(|v| v.as_numeric())
+            .unwrap();
+        match id.data() {
+            super::NumericData::Double { real, .. } => assert_eq!(real, &vec![3.0]),
+            _ => panic!("expected double data"),
+        }
+        let duplicate = super::StructBuilder::new()
+            .field("a", 1.0)
+            .field("a", 2.0)
+            .build("d");
+        assert!(duplicate.is_err());
+        for field in ["my gain", "1x", "_x", "gain.x", "增益", ""] {
+            let invalid = super::StructBuilder::new().field(field, 1.0).build("d");
+            assert!(invalid.is_err(), "{:?}", field);
+        }
+        assert!(super::StructBuilder::new()
+            .field("x_1", 1.0)
+            .build("d")
+            .is_ok());
+        Ok(())
+    }
+    #[test]
+    fn int32_and_empty_complex_round_trip() -> std::result::Result<(), crate::mat_error::MatError> {
+        use crate::parse::{DataType, MatlabType};
+        //int32数组的数据子元素类型必须是miINT32
+        assert_eq!(MatlabType::Int32.numeric_data_type(), Some(DataType::Int32));
+        let mut matfile = super::MatFile::new_mat_file();
+        matfile.add_array(super::Array::from_vec("n", &[1, 3], vec![-1i32, 0, 7])?);
+        //空的复数数组也要写出虚部子元素
+        matfile.add_array(super::Array::from_complex::<f64>(
+            "e",
+            &[0, 0],
+            vec![],
+            vec![],
+        )?);
+        let matfile = round_trip(&matfile)?;
+        let n = matfile
+            .find_by_name("n")
+            .and_then(|v| v.as_numeric())
+            .unwrap();
+        assert_eq!(n.as_slice::<i32>()?, &[-1, 0, 7]);
+        let e = matfile
+            .find_by_name("e")
+            .and_then(|v| v.as_numeric())
+            .unwrap();
+        assert!(e.data().is_complex());
+        assert_eq!(e.size(), &vec![0, 0]);
+        Ok(())
+    }
+
+    #[test]
+    fn sparse_array_round_trip() -> std::result::Result<(), crate::mat_error::MatError> {
+        //重复的(0,1)会被合并
+        let graph = super::SparseArray::from_triplets(
+            "graph",
+            4,
+            3,
+            &[(3, 2, 1.0), (0, 1, 2.0), (0, 1, 0.5), (2, 0, -1.0)],
+        )?;
+        assert_eq!(graph.jc(), &[0, 1, 2, 3]);
+        let mask = super::SparseArray::from_triplets(
+            "mask",
+            2,
+            2,
+            &[(0, 0, 3.0), (1, 0, 0.0), (1, 1, 1.0)],
+        )?
+        .into_logical()?;
+        let impedance = super::SparseArray::from_complex_triplets(
+            "impedance",
+            2,
+            2,
+            &[(1, 1, 50.0, -3.0), (0, 0, 0.0, 1.0)],
+        )?;
+        let mut matfile = super::MatFile::new_mat_file();
+        matfile.add_array(graph);
+        matfile.add_array(super::SparseArray::from_csc(
+            "empty",
+            4,
+            3,
+            vec![],
+            vec![0, 0, 0, 0],
+            vec![],
+            None,
+        )?);
+        matfile.add_array(mask);
+        matfile.add_array(impedance);
+        let matfile = round_trip(&matfile)?;
+        let graph = matfile
+            .find_by_name("graph")
+            .and_then(|v| v.as_sparse())
+            .unwrap();
+        assert_eq!(graph.nzmax(), 3);
+        let triplets: Vec<_> = graph.triplets().collect();
+        assert_eq!(triplets, vec![(2, 0, -1.0), (0, 1, 2.5), (3, 2, 1.0)]);
+        let mask = matfile
+            .find_by_name("mask")
+            .and_then(|v| v.as_sparse())
+            .unwrap();
+        assert!(mask.is_logical());
+        assert_eq!(mask.ir(), &[0, 1]);
+        assert_eq!(mask.real(), &[1.0, 1.0]);
+        let empty = matfile
+            .find_by_name("empty")
+            .and_then(|v| v.as_sparse())
+            .unwrap();
+        assert_eq!(empty.nnz(), 0);
+        let impedance = matfile
+            .find_by_name("impedance")
+            .and_then(|v| v.as_sparse())
+            .unwrap();
+        assert!(impedance.is_complex());
+        assert_eq!(impedance.ir(), &[0, 1]);
+        assert_eq!(impedance.imag().unwrap(), &[1.0, -3.0]);
+        assert!(
+            super::SparseArray::from_csc("bad", 2, 1, vec![5], vec![0, 1], vec![1.0], None)
+                .is_err()
+        );
+        //同一列中行号无序或重复
+        for ir in [vec![1, 0], vec![1, 1]] {
+            let csc =
+                super::SparseArray::from_csc("bad", 2, 2, ir, vec![0, 2, 2], vec![1.0; 2], None);
+            assert!(csc.is_err());
+        }
+        //行号只需在列内递增
+        assert!(super::SparseArray::from_csc(
+            "ok",
+            2,
+            2,
+            vec![1, 0],
+            vec![0, 1, 2],
+            vec![1.0; 2],
+            None
+        )
+        .is_ok());
+        Ok(())
+    }
+    #[test]
+    fn logical_array_round_trip() -> std::result::Result<(), crate::mat_error::MatError> {
+        let mask = super::LogicalArray::new("mask", &[2, 2], vec![true, false, false, true])?;
+        let dense = super::SparseArray::from_triplets("dense", 2, 1, &[(1, 0, 1.0)])?
+            .into_logical()?
+            .to_dense();
+        let mut matfile = super::MatFile::new_mat_file();
+        matfile.add_array(mask);
+        matfile.add_array(super::LogicalArray::from_slice(
+            "valid",
+            &[true, true, false],
+        ));
+        matfile.add_array(dense);
+        let matfile = round_trip(&matfile)?;
+        let mask = matfile
+            .find_by_name("mask")
+            .and_then(|v| v.as_logical())
+            .unwrap();
+        assert_eq!(mask.size(), &vec![2, 2]);
+        assert_eq!(mask.get_at(&[1, 1]), Some(true));
+        assert_eq!(mask.get_at(&[0, 1]), Some(false));
+        let valid = matfile
+            .find_by_name("valid")
+            .and_then(|v| v.as_logical())
+            .unwrap();
+        assert_eq!(valid.data(), &[true, true, false]);
+        let dense = matfile
+            .find_by_name("dense")
+            .and_then(|v| v.as_logical())
+            .unwrap();
+        assert_eq!(dense.data(), &[false, true]);
+        assert!(super::LogicalArray::new("bad", &[2, 2], vec![true]).is_err());
+        Ok(())
+    }
+    #[test]
+    fn typed_element_access() -> std::result::Result<(), crate::mat_error::MatError> {
+        let mut array = super::Array::double("a", vec![2, 2, 2], (0..8).map(f64::from).collect());
+        assert_eq!(array.get::<f64>(&[1, 0, 1])?, 5.0);
+        array.set(&[0, 1, 1], -1.0)?;
+        assert_eq!(array.as_slice::<f64>()?[6], -1.0);
+        array.as_mut_slice::<f64>()?[7] = 9.0;
+        assert_eq!(array.get::<f64>(&[1, 1, 1])?, 9.0);
+        match array.get::<i32>(&[0, 0, 0]) {
+            Err(crate::mat_error::MatError::ClassMismatch { expected, found }) => {
+                assert_eq!(expected, super::MatlabType::Int32);
+                assert_eq!(found, super::MatlabType::Double);
+            }
+            _ => panic!("expected a class mismatch"),
+        }
+        assert!(array.set(&[2, 0, 0], 1.0).is_err());
+        Ok(())
+    }
+    #[test]
+    fn subscript_conversion() -> std::result::Result<(), crate::mat_error::MatError> {
+        use crate::mat_error::MatError;
+        let array = super::Array::double("a", vec![2, 3, 4], vec![0.0; 24]);
+        assert_eq!(array.sub2ind(&[1, 2, 3])?, 23);
+        assert_eq!(array.sub2ind(&[1, 2, 3, 0, 0])?, 23);
+        assert_eq!(array.sub2ind(&[1, 11])?, 23);
+        assert_eq!(array.sub2ind(&[23])?, 23);
+        assert_eq!(array.ind2sub(23)?, vec![1, 2, 3]);
+        assert_eq!(array.ind2sub(array.sub2ind(&[0, 1, 2])?)?, vec![0, 1, 2]);
+        match array.sub2ind(&[2, 0, 0]) {
+            Err(MatError::IndexOutOfBounds { index, size }) => {
+                assert_eq!(index, vec![2, 0, 0]);
+                assert_eq!(size, vec![2, 3, 4]);
+            }
+            _ => panic!("expected an out-of-bounds error"),
+        }
+        assert!(array.sub2ind(&[0, 0, 0, 1]).is_err());
+        assert!(array.sub2ind(&[0, 12]).is_err());
+        assert!(array.sub2ind(&[]).is_err());
+        assert!(array.ind2sub(24).is_err());
+        assert_eq!(array.check_index_bound(1, 0)?, 1);
+        assert!(array.check_index_bound(3, 1).is_err());
+        assert_eq!(array.check_index_bound(0, 5)?, 0);
+
+        let mut int8 = super::MatFile::new_matrix("b", 2, 2, false, super::MatlabType::Int8)?;
+        assert!(int8.set_int8(2, 0, 1).is_err());
+        int8.set_int8(1, 1, 4)?;
+        assert_eq!(int8.get::<i8>(&[3])?, 4);
+        Ok(())
+    }
+    #[test]
+    fn array_constructors_round_trip() -> std::result::Result<(), crate::mat_error::MatError> {
+        let cube = super::Array::from_vec("cube", &[2, 1, 3], vec![-1i32, 2, -3, 4, -5, 6])?;
+        let z = super::Array::from_complex("z", &[1, 2], vec![1.5, -2.0], vec![0.5, 3.0])?;
+        let empty = super::Array::from_vec::<f64>("empty", &[0, 3], vec![])?;
+        let row = super::Array::from_vec("row", &[3], vec![1u16, 2, 3])?;
+        assert_eq!(row.size(), &vec![1, 3]);
+        assert!(super::Array::from_vec("bad", &[2, 2], vec![1.0]).is_err());
+        assert!(super::Array::from_complex("bad", &[1, 2], vec![1.0, 2.0], vec![1.0]).is_err());
+        let mut matfile = super::MatFile::new_mat_file();
+        matfile.add_array(cube);
+        matfile.add_array(z);
+        matfile.add_array(empty);
+        matfile.add_array(super::Array::scalar("gain", 0.25f32));
+        let matfile = round_trip(&matfile)?;
+        let array = |name| {
+            matfile
+                .find_by_name(name)
+                .and_then(|v| v.as_numeric())
+                .unwrap()
+        };
+        assert_eq!(array("cube").size(), &vec![2, 1, 3]);
+        assert_eq!(array("cube").get::<i32>(&[0, 0, 2])?, -5);
+        match array("z").data() {
+            super::NumericData::Double { real, imag } => {
+                assert_eq!(real, &vec![1.5, -2.0]);
+                assert_eq!(imag, &Some(vec![0.5, 3.0]));
+            }
+            _ => panic!("expected complex double data"),
+        }
+        assert_eq!(array("empty").size(), &vec![0, 3]);
+        assert_eq!(array("gain").get::<f32>(&[0, 0])?, 0.25);
+        Ok(())
+    }
+    #[test]
+    fn complex_elements() -> std::result::Result<(), crate::mat_error::MatError> {
+        use num_complex::Complex;
+        let spectrum = vec![Complex::new(1.0, -1.0), Complex::new(0.5, 2.0)];
+        let mut array = super::Array::from_complex_vec("fft", &[2, 1], spectrum.clone())?;
+        assert_eq!(array.get_complex::<f64>(&[1, 0])?, Complex::new(0.5, 2.0));
+        assert_eq!(array.iter_complex::<f64>()?.collect::<Vec<_>>(), spectrum);
+        array.set_complex(&[0, 0], Complex::new(3.0, 4.0))?;
+        assert_eq!(array.get::<f64>(&[0, 0])?, 3.0);
+        assert!(array.get_complex::<f32>(&[0, 0]).is_err());
+        //失败时数据保持不变
+        let mut unchanged = super::Array::from_vec("u", &[1, 2], vec![1.0, 2.0])?;
+        assert!(unchanged
+            .set_complex(&[0, 2], Complex::new(1.0, 1.0))
+            .is_err());
+        assert!(unchanged
+            .set_complex(&[0, 0], Complex::new(1f32, 1.0))
+            .is_err());
+        assert_eq!(unchanged.as_slice::<f64>()?, &[1.0, 2.0]);
+        assert!(!unchanged.data().is_complex());
+
+        let mut real = super::Array::from_vec("re", &[1, 2], vec![1i16, 2])?;
+        assert_eq!(real.get_complex::<i16>(&[0, 1])?, Complex::new(2, 0));
+        real.set_complex(&[0, 1], Complex::new(5i16, -6))?;
+        assert_eq!(
+            real.iter_complex::<i16>()?.collect::<Vec<_>>(),
+            vec![Complex::new(1, 0), Complex::new(5, -6)]
+        );
+        let mut matfile = super::MatFile::new_mat_file();
+        matfile.add_array(real);
+        let matfile = round_trip(&matfile)?;
+        let real = matfile
+            .find_by_name("re")
+            .and_then(|v| v.as_numeric())
+            .unwrap();
+        assert_eq!(real.get_complex::<i16>(&[1])?, Complex::new(5, -6));
+        Ok(())
+    }
+    #[test]
+    fn cast_follows_matlab_rules() -> std::result::Result<(), crate::mat_error::MatError> {
+        use super::MatlabType;
+        let values = vec![2.5, -2.5, 300.0, -1.4, f64::NAN, f64::INFINITY, -0.5];
+        let array = super::Array::from_vec("x", &[1, 7], values)?;
+        let uint8 = array.cast(MatlabType::UInt8)?;
+        assert_eq!(uint8.as_slice::<u8>()?, &[3, 0, 255, 0, 0, 255, 0]);
+        let int8 = array.cast(MatlabType::Int8)?;
+        assert_eq!(int8.as_slice::<i8>()?, &[3, -3, 127, -1, 0, 127, -1]);
+        assert_eq!(array.cast(MatlabType::Single)?.get::<f32>(&[2])?, 300.0);
+        assert!(array.cast(MatlabType::Cell).is_err());
+
+        let wide = super::Array::from_vec("w", &[1, 3], vec![u32::MAX, 7, 0])?;
+        assert_eq!(
+            wide.cast(MatlabType::Int64)?.as_slice::<i64>()?,
+            &[u32::MAX as i64, 7, 0]
+        );
+        assert_eq!(
+            wide.cast(MatlabType::Int16)?.as_slice::<i16>()?,
+            &[i16::MAX, 7, 0]
+        );
+        let negative = super::Array::from_vec("n", &[1, 2], vec![-128i8, 5])?;
+        assert_eq!(
+            negative.cast(MatlabType::Double)?.as_slice::<f64>()?,
+            &[-128.0, 5.0]
+        );
+        assert_eq!(
+            negative.cast(MatlabType::UInt64)?.as_slice::<u64>()?,
+            &[0, 5]
+        );
+
+        let z = super::Array::from_complex("z", &[1, 1], vec![1.5f32], vec![-0.5])?;
+        let z = z.cast(MatlabType::Int32)?;
+        assert_eq!(
+            z.get_complex::<i32>(&[0])?,
+            num_complex::Complex::new(2, -1)
+        );
+
+        let element = crate::parse::DataElement::NumericMatrix(
+            crate::parse::ArrayFlags {
+                complex: true,
+                global: false,
+                logical: false,
+                class: MatlabType::Double,
+                nzmax: 0,
+            },
+            vec![1, 2],
+            "stored".to_string(),
+            crate::parse::NumericData::Int8(vec![-3, 4]),
+            Some(crate::parse::NumericData::UInt32(vec![1, 70000])),
+        );
+        let value = super::MatValue::try_from(element)?;
+        let stored = value.as_numeric().unwrap();
+        assert_eq!(
+            stored.get_complex::<f64>(&[1])?,
+            num_complex::Complex::new(4.0, 70000.0)
+        );
+        assert_eq!(stored.get::<f64>(&[0])?, -3.0);
+        Ok(())
+    }
+    //按MATLAB的方式写出一个数值矩阵元素,不超过4字节的数据使用small格式
+    fn stored_matrix(class: u8, name: &str, dims: &[i32], storage: u32, data: &[u8]) -> Vec<u8> {
+        use crate::test_fixtures::{matrix, small_subelement};
+        matrix(class, Some(dims), name, &small_subelement(storage, data))
+    }
+    #[test]
+    fn read_every_storage_type() -> std::result::Result<(), crate::mat_error::MatError> {
+        use super::MatlabType;
+        let values = [0u8, 1, 100];
+        let storages: [(u32, Vec<u8>); 10] = [
+            (
+                1,
+                values
+                    .iter()
+                    .flat_map(|&v| (v as i8).to_le_bytes())
+                    .collect(),
+            ),
+            (2, values.to_vec()),
+            (
+                3,
+                values
+                    .iter()
+                    .flat_map(|&v| (v as i16).to_le_bytes())
+                    .collect(),
+            ),
+            (
+                4,
+                values
+                    .iter()
+                    .flat_map(|&v| (v as u16).to_le_bytes())
+                    .collect(),
+            ),
+            (
+                5,
+                values
+                    .iter()
+                    .flat_map(|&v| (v as i32).to_le_bytes())
+                    .collect(),
+            ),
+            (
+                6,
+                values
+                    .iter()
+                    .flat_map(|&v| (v as u32).to_le_bytes())
+                    .collect(),
+            ),
+            (
+                7,
+                values
+                    .iter()
+                    .flat_map(|&v| (v as f32).to_le_bytes())
+                    .collect(),
+            ),
+            (
+                9,
+                values
+                    .iter()
+                    .flat_map(|&v| (v as f64).to_le_bytes())
+                    .collect(),
+            ),
+            (
+                12,
+                values
+                    .iter()
+                    .flat_map(|&v| (v as i64).to_le_bytes())
+                    .collect(),
+            ),
+            (
+                13,
+                values
+                    .iter()
+                    .flat_map(|&v| (v as u64).to_le_bytes())
+                    .collect(),
+            ),
+        ];
+        let classes = [
+            MatlabType::Double,
+            MatlabType::Single,
+            MatlabType::Int8,
+            MatlabType::UInt8,
+            MatlabType::Int16,
+            MatlabType::UInt16,
+            MatlabType::Int32,
+            MatlabType::UInt32,
+            MatlabType::Int64,
+            MatlabType::UInt64,
+        ];
+        let mut elements = Vec::new();
+        for &class in classes.iter() {
+            for (storage, data) in storages.iter() {
+                let name = format!("v{}_{}", class as u8, storage);
+                elements.push(stored_matrix(class as u8, &name, &[1, 3], *storage, data));
+            }
+        }
+        let matfile = super::MatFile::parse(&crate::test_fixtures::mat_file(&elements, None)[..])?;
+        assert_eq!(matfile.values().len(), 100);
+        for value in matfile.values() {
+            let array = value.as_numeric().unwrap();
+            let class = array.data().class();
+            assert_eq!(
+                format!("v{}", class as u8),
+                array.name().split('_').next().unwrap()
+            );
+            let double = array.cast(MatlabType::Double)?;
+            assert_eq!(
+                double.as_slice::<f64>()?,
+                &[0.0, 1.0, 100.0],
+                "{}",
+                array.name()
+            );
+        }
+        Ok(())
+    }
+    #[test]
+    fn read_compacted_negative_doubles() -> std::result::Result<(), crate::mat_error::MatError> {
+        //MATLAB把 x = [-1 -2 3] 压缩为3字节的miINT8并使用small格式
+        let negative = stored_matrix(6, "x", &[1, 3], 1, &[0xFF, 0xFE, 0x03]);
+        let large = stored_matrix(6, "y", &[1, 2], 5, &[0, 0, 0, 0x80, 0xFF, 0xFF, 0xFF, 0x7F]);
+        let empty = stored_matrix(6, "z", &[0, 0], 9, &[]);
+        let matfile = super::MatFile::parse(
+            &crate::test_fixtures::mat_file(&[negative, large, empty], None)[..],
+        )?;
+        let array = |name| {
+            matfile
+                .find_by_name(name)
+                .and_then(|v| v.as_numeric())
+                .unwrap()
+        };
+        assert_eq!(array("x").as_slice::<f64>()?, &[-1.0, -2.0, 3.0]);
+        assert_eq!(
+            array("y").as_slice::<f64>()?,
+            &[i32::MIN as f64, i32::MAX as f64]
+        );
+        assert_eq!(array("z").as_slice::<f64>()?, &[] as &[f64]);
+        Ok(())
+    }
+    #[test]
+    fn datetime_to_utc() {
+        use chrono::NaiveDate;
+        let time = NaiveDate::from_ymd_opt(2020, 3, 29)
+            .unwrap()
+            .and_hms_opt(1, 30, 0)
+            .unwrap();
+        let mut times = super::DateTimeArray {
+            name: "t".to_string(),
+            size: vec![1, 2],
+            data: vec![Some(time), None],
+            time_zone: Some("Europe/Berlin".to_string()),
+            format: String::new(),
+        };
+        //命名时区没有固定偏移,但数据本身就是UTC时间
+        assert!(times.to_fixed_offset().is_none());
+        let utc = times.to_utc().unwrap();
+        assert_eq!(utc[0].unwrap().naive_utc(), time);
+        assert_eq!(utc[1], None);
+        times.time_zone = None;
+        assert!(times.to_utc().is_none());
+    }
+    #[test]
+    fn reject_numeric_data_not_matching_dims() {
+        use crate::test_fixtures::{matrix, subelement};
+        let doubles = |values: &[f64]| -> Vec<u8> {
+            subelement(
+                9,
+                &values
+                    .iter()
+                    .flat_map(|v| v.to_le_bytes())
+                    .collect::<Vec<u8>>(),
+            )
+        };
+        //2x2的矩阵只有3个元素
+        let short = matrix(6, Some(&[2, 2]), "x", &doubles(&[1.0, 2.0, 3.0]));
+        assert!(
+            super::MatFile::parse(&crate::test_fixtures::mat_file(&[short], None)[..]).is_err()
+        );
+        //虚部比实部少一个元素
+        let dims: Vec<u8> = [2i32, 2].iter().flat_map(|d| d.to_le_bytes()).collect();
+        let mut body = subelement(6, &[6, 0x08, 0, 0, 0, 0, 0, 0]);
+        body.extend(subelement(5, &dims));
+        body.extend(subelement(1, b"z"));
+        body.extend(doubles(&[1.0, 2.0, 3.0, 4.0]));
+        body.extend(doubles(&[1.0, 2.0, 3.0]));
+        let complex = subelement(14, &body);
+        assert!(
+            super::MatFile::parse(&crate::test_fixtures::mat_file(&[complex], None)[..]).is_err()
+        );
+    }
 }
@@ -1,3 +1,5 @@
+use crate::parse::MatlabType;
+
 #[derive(Debug)]
 pub enum MatError {
     IOError(std::io::Error),
@@ -5,6 +7,14 @@ pub enum MatError {
     ConversionError,
     InternalError,
     ParamsError(String),
+    ClassMismatch {
+        expected: MatlabType,
+        found: MatlabType,
+    },
+    IndexOutOfBounds {
+        index: Vec<usize>,
+        size: Vec<usize>,
+    },
 }
 
 impl std::fmt::Display for MatError {
@@ -17,6 +27,12 @@ impl std::fmt::Display for MatError {
             }
             MatError::InternalError => write!(f, "An internal error occurred, this is a bug"),
             MatError::ParamsError(_) => write!(f, "Params bug"),
+            MatError::ClassMismatch { expected, found } => {
+                write!(f, "Expected a {:?} array but found {:?}", expected, found)
+            }
+            MatError::IndexOutOfBounds { index, size } => {
+                write!(f, "Index {:?} is out of bounds for size {:?}", index, size)
+            }
         }
     }
 }
@@ -0,0 +1,1256 @@
+//! Decoding of classdef (MCOS) objects.
+//!
+//! Variables holding classdef objects only store a small reference. The
+//! objects themselves live in the subsystem data at `subsys_offset`, inside a
+//! `FileWrapper__` opaque whose cells hold the metadata tables, the property
+//! values and the default property values of each class.
+use chrono::{DateTime, Duration};
+use nom::number::Endianness;
+
+use crate::mat_error::MatError;
+use crate::{
+    parse, Array, Categorical, DateTimeArray, DurationArray, MatElement, MatValue, MatlabType,
+    NumericData, ObjectArray, StringArray, StructArray, Table,
+};
+
+//对象引用uint32数组的第一个元素
+const REFERENCE_MARKER: u32 = 0xDD00_0000;
+
+#[derive(Clone, Debug)]
+struct ObjectInfo {
+    class_id: usize,
+    saveobj_id: usize,
+    normobj_id: usize,
+}
+
+#[derive(Clone, Debug)]
+struct Property {
+    name_index: usize,
+    kind: usize,
+    value: usize,
+}
+
+/// The MCOS tables read from the subsystem data of a file.
+#[derive(Clone, Debug)]
+pub(crate) struct Subsystem {
+    endianness: Endianness,
+    //名称表,索引从1开始
+    names: Vec<String>,
+    //按类编号排列,0号为空
+    classes: Vec<String>,
+    //按对象编号排列,0号为空
+    objects: Vec<ObjectInfo>,
+    saveobj_properties: Vec<Vec<Property>>,
+    normobj_properties: Vec<Vec<Property>>,
+    values: Vec<MatValue>,
+    defaults: Vec<Vec<(String, MatValue)>>,
+}
+
+impl Subsystem {
+    /// Reads the subsystem element, `None` if it holds no MCOS data.
+    pub(crate) fn parse(
+        element: parse::DataElement,
+        endianness: Endianness,
+    ) -> Result<Option<Subsystem>, MatError> {
+        let bytes = match element {
+            parse::DataElement::NumericMatrix(flags, _, _, real, _)
+                if flags.class == MatlabType::UInt8 =>
+            {
+                to_bytes(real)?
+            }
+            _ => return Ok(None),
+        };
+        let (_, elements) = parse::parse_subsystem(&bytes, endianness)
+            .map_err(|err| MatError::ParseError(parse::replace_err_slice(err, &[])))?;
+        let mut cells = match elements.into_iter().find_map(find_file_wrapper) {
+            Some(cells) => cells,
+            None => return Ok(None),
+        };
+        //依次为元数据、一个占位元素、属性值,最后是各类的默认属性值
+        if cells.len() < 2 {
+            return Err(invalid_metadata());
+        }
+        let defaults = if cells.len() > 2 { cells.pop() } else { None };
+        let mut cells = cells.into_iter();
+        let metadata = match cells.next() {
+            Some(parse::DataElement::NumericMatrix(_, _, _, real, _)) => to_bytes(real)?,
+            _ => return Err(invalid_metadata()),
+        };
+        let mut subsystem =
+            Subsystem::read_metadata(&metadata, endianness).ok_or_else(invalid_metadata)?;
+        for cell in cells.skip(1) {
+            subsystem.values.push(MatValue::try_from(cell)?);
+        }
+        if let Some(defaults) = defaults {
+            subsystem.defaults = read_defaults(defaults)?;
+        }
+        Ok(Some(subsystem))
+    }
+
+    //元数据:版本号、名称个数、8个区域偏移,之后是以0分隔的名称表
+    fn read_metadata(metadata: &[u8], endianness: Endianness) -> Option<Subsystem> {
+        let word = |offset: usize| read_u32(metadata, offset, endianness);
+        let num_names = word(4)?;
+        let offsets = (0..8)
+            .map(|i| word(8 + 4 * i))
+            .collect::<Option<Vec<usize>>>()?;
+        let names = metadata
+            .get(40..offsets[0])?
+            .split(|&b| b == 0)
+            .take(num_names)
+            .map(|name| String::from_utf8_lossy(name).into_owned())
+            .collect();
+        let mut subsystem = Subsystem {
+            endianness,
+            names,
+            classes: vec![],
+            objects: vec![],
+            saveobj_properties: vec![],
+            normobj_properties: vec![],
+            values: vec![],
+            defaults: vec![],
+        };
+        //类:包名和类名的名称索引,之后两个保留字
+        for entry in (offsets[0]..offsets[1]).step_by(16) {
+            let package = subsystem.name(word(entry)?).unwrap_or("");
+            let class = subsystem.name(word(entry + 4)?).unwrap_or("");
+            let class_name = if package.is_empty() {
+                class.to_string()
+            } else {
+                format!("{}.{}", package, class)
+            };
+            subsystem.classes.push(class_name);
+        }
+        subsystem.saveobj_properties =
+            read_property_blocks(metadata, offsets[1], offsets[2], endianness)?;
+        //对象:类编号、两个保留字、saveobj属性编号、普通属性编号、对象编号
+        for entry in (offsets[2]..offsets[3]).step_by(24) {
+            subsystem.objects.push(ObjectInfo {
+                class_id: word(entry)?,
+                saveobj_id: word(entry + 12)?,
+                normobj_id: word(entry + 16)?,
+            });
+        }
+        subsystem.normobj_properties =
+            read_property_blocks(metadata, offsets[3], offsets[4], endianness)?;
+        Some(subsystem)
+    }
+
+    /// Replaces MCOS references in a value, recursively, with the objects they point to.
+    ///
+    /// References that cannot be resolved are kept as they are.
+    pub(crate) fn resolve(&self, value: MatValue) -> MatValue {
+        self.resolve_value(value, false, &mut vec![])
+    }
+
+    //属性值中的对象只保存为uint32引用数组,没有opaque包装
+    fn resolve_value(&self, value: MatValue, bare: bool, stack: &mut Vec<usize>) -> MatValue {
+        match value {
+            MatValue::Opaque(opaque) if opaque.object_type == "MCOS" => {
+                let object = opaque.data.as_numeric().and_then(|reference| {
+                    self.resolve_reference(&opaque.name, &opaque.class_name, reference, stack)
+                });
+                object.unwrap_or(MatValue::Opaque(opaque))
+            }
+            MatValue::Numeric(array) if bare => self
+                .resolve_reference(&array.name, "", &array, stack)
+                .unwrap_or(MatValue::Numeric(array)),
+            MatValue::Cell(mut cell) => {
+                cell.data = self.resolve_all(cell.data, bare, stack);
+                MatValue::Cell(cell)
+            }
+            MatValue::Struct(mut array) => {
+                array.data = self.resolve_all(array.data, bare, stack);
+                MatValue::Struct(array)
+            }
+            MatValue::Object(mut object) => {
+                object.data.data = self.resolve_all(object.data.data, bare, stack);
+                MatValue::Object(object)
+            }
+            value => value,
+        }
+    }
+
+    fn resolve_all(
+        &self,
+        values: Vec<MatValue>,
+        bare: bool,
+        stack: &mut Vec<usize>,
+    ) -> Vec<MatValue> {
+        values
+            .into_iter()
+            .map(|value| self.resolve_value(value, bare, stack))
+            .collect()
+    }
+
+    fn resolve_reference(
+        &self,
+        name: &str,
+        class_name: &str,
+        reference: &Array,
+        stack: &mut Vec<usize>,
+    ) -> Option<MatValue> {
+        let (size, ids, class_id) = read_reference(reference)?;
+        let num_objects = ids.len();
+        let class_name = if class_name.is_empty() {
+            self.classes.get(class_id)?.clone()
+        } else {
+            class_name.to_string()
+        };
+        let mut field_names: Vec<String> = vec![];
+        let mut objects = Vec::with_capacity(num_objects);
+        for &id in ids {
+            let properties = self.object_properties(id as usize, stack)?;
+            for (field, _) in &properties {
+                if !field_names.contains(field) {
+                    field_names.push(field.clone());
+                }
+            }
+            objects.push(properties);
+        }
+        //对象数组中各元素的属性可能不同,缺少的属性为空矩阵
+        let mut data = Vec::with_capacity(num_objects * field_names.len());
+        for mut properties in objects {
+            for field in &field_names {
+                data.push(
+                    match properties.iter().position(|(name, _)| name == field) {
+                        Some(position) => properties.swap_remove(position).1,
+                        None => MatValue::Numeric(Array::double("", vec![0, 0], vec![])),
+                    },
+                );
+            }
+        }
+        let data = StructArray::new(name, &size, field_names, data).ok()?;
+        Some(self.decode(ObjectArray { class_name, data }))
+    }
+
+    //已知的类转换为对应的类型,无法转换时保留为对象
+    fn decode(&self, mut object: ObjectArray) -> MatValue {
+        let value = match object.class_name.as_str() {
+            "string" => self.decode_string(&object).map(MatValue::String),
+            "datetime" => decode_datetime(&object).map(MatValue::DateTime),
+            "duration" => decode_duration(&object).map(MatValue::Duration),
+            "table" => decode_table(&mut object, false).map(MatValue::Table),
+            "timetable" => decode_table(&mut object, true).map(MatValue::Table),
+            "categorical" => decode_categorical(&object).map(MatValue::Categorical),
+            _ => None,
+        };
+        value.unwrap_or(MatValue::Object(object))
+    }
+
+    //any属性为uint64数组:版本号、维数个数、各维大小、各字符串长度,之后是按UTF-16编码的字符。
+    //长度为u64::MAX表示缺失的字符串
+    fn decode_string(&self, object: &ObjectArray) -> Option<StringArray> {
+        let words = object
+            .data
+            .get(0, "any")?
+            .as_numeric()?
+            .as_slice::<u64>()
+            .ok()?;
+        let (&ndims, words) = words.get(1..)?.split_first()?;
+        let ndims = ndims as usize;
+        if words.len() < ndims {
+            return None;
+        }
+        let (dims, words) = words.split_at(ndims);
+        let size: Vec<usize> = dims.iter().map(|&d| d as usize).collect();
+        let num_strings = size
+            .iter()
+            .try_fold(1usize, |product, &d| product.checked_mul(d))?;
+        if words.len() < num_strings {
+            return None;
+        }
+        let (lengths, words) = words.split_at(num_strings);
+        let chars: Vec<u16> = words
+            .iter()
+            .flat_map(|&word| match self.endianness {
+                Endianness::Big => word.to_be_bytes(),
+                _ => word.to_le_bytes(),
+            })
+            .collect::<Vec<u8>>()
+            .chunks_exact(2)
+            .map(|pair| match self.endianness {
+                Endianness::Big => u16::from_be_bytes([pair[0], pair[1]]),
+                _ => u16::from_le_bytes([pair[0], pair[1]]),
+            })
+            .collect();
+        let mut offset = 0usize;
+        let mut data = Vec::with_capacity(num_strings);
+        for &length in lengths {
+            if length == u64::MAX {
+                data.push(None);
+                continue;
+            }
+            let text = chars.get(offset..offset.checked_add(length as usize)?)?;
+            data.push(Some(String::from_utf16_lossy(text)));
+            offset += length as usize;
+        }
+        StringArray::new(object.name(), &size, data).ok()
+    }
+
+    //对象的属性以类的默认值为基础,再用保存的属性值覆盖
+    fn object_properties(
+        &self,
+        id: usize,
+        stack: &mut Vec<usize>,
+    ) -> Option<Vec<(String, MatValue)>> {
+        if stack.contains(&id) {
+            return None;
+        }
+        let object = self.objects.get(id)?;
+        let saved = if object.saveobj_id != 0 {
+            self.saveobj_properties.get(object.saveobj_id)?
+        } else {
+            self.normobj_properties.get(object.normobj_id)?
+        };
+        stack.push(id);
+        let defaults = self.defaults.get(object.class_id).map(|defaults| {
+            defaults
+                .iter()
+                .map(|(name, value)| (name.clone(), self.resolve_value(value.clone(), true, stack)))
+                .collect::<Vec<_>>()
+        });
+        let saved = saved
+            .iter()
+            .map(|property| self.property_value(property, stack))
+            .collect::<Option<Vec<_>>>();
+        stack.pop();
+        let mut properties = defaults.unwrap_or_default();
+        for (name, value) in saved? {
+            match properties.iter_mut().find(|(field, _)| *field == name) {
+                Some(property) => property.1 = value,
+                None => properties.push((name, value)),
+            }
+        }
+        Some(properties)
+    }
+
+    //属性类型0为名称表中的字符串,1为属性值单元的编号,2为直接保存的数值
+    fn property_value(
+        &self,
+        property: &Property,
+        stack: &mut Vec<usize>,
+    ) -> Option<(String, MatValue)> {
+        let name = self.name(property.name_index)?.to_string();
+        let value = match property.kind {
+            0 => MatValue::from(self.name(property.value)?),
+            1 => {
+                let value = self.values.get(property.value)?.clone();
+                self.resolve_value(value, true, stack)
+            }
+            2 => MatValue::from(property.value as f64),
+            _ => return None,
+        };
+        Some((name, value))
+    }
+
+    fn name(&self, index: usize) -> Option<&str> {
+        let name = self.names.get(index.checked_sub(1)?)?;
+        Some(name.as_str())
+    }
+}
+
+//data属性为自1970-01-01起的毫秒数,复数的虚部保存不足1毫秒的部分,NaN表示NaT。
+//有时区时为UTC时间,否则为本地时间
+fn decode_datetime(object: &ObjectArray) -> Option<DateTimeArray> {
+    let millis = object.data.get(0, "data")?.as_numeric()?;
+    let data = millis
+        .iter_complex::<f64>()
+        .ok()?
+        .map(|millis| {
+            if !millis.re.is_finite() || !millis.im.is_finite() {
+                return None;
+            }
+            let whole = millis.re.floor();
+            let nanos = ((millis.re - whole + millis.im) * 1e6).round() as i64;
+            DateTime::UNIX_EPOCH
+                .naive_utc()
+                .checked_add_signed(Duration::try_milliseconds(whole as i64)?)?
+                .checked_add_signed(Duration::nanoseconds(nanos))
+        })
+        .collect();
+    Some(DateTimeArray {
+        name: object.name().to_string(),
+        size: millis.size().clone(),
+        data,
+        time_zone: text_property(object, "tz").filter(|zone| !zone.is_empty()),
+        format: text_property(object, "fmt").unwrap_or_default(),
+    })
+}
+
+//millis属性为毫秒数
+fn decode_duration(object: &ObjectArray) -> Option<DurationArray> {
+    let millis = object.data.get(0, "millis")?.as_numeric()?;
+    let data = millis
+        .as_slice::<f64>()
+        .ok()?
+        .iter()
+        .map(|&millis| {
+            if millis.is_finite() {
+                Some(Duration::nanoseconds((millis * 1e6).round() as i64))
+            } else {
+                None
+            }
+        })
+        .collect();
+    Some(DurationArray {
+        name: object.name().to_string(),
+        size: millis.size().clone(),
+        data,
+        format: text_property(object, "fmt").unwrap_or_default(),
+    })
+}
+
+//data属性是按列保存的cell,table和timetable的其他属性名称不同。
+//各列在检查完所有属性之后才从对象中取出,失败时对象保持不变
+//与已知结构不符时返回None,保留为对象
+fn decode_table(object: &mut ObjectArray, timetable: bool) -> Option<Table> {
+    let (names_field, rows_field) = if timetable {
+        ("varNames", "numRows")
+    } else {
+        ("varnames", "nrows")
+    };
+    if object.data.get_num_elements() != 1 {
+        return None;
+    }
+    let name = object.name().to_string();
+    let state = table_state(&mut object.data)?;
+    let columns = state.get(0, "data")?.as_cell()?;
+    let variable_names = text_list(state.get(0, names_field)?)?;
+    if variable_names.len() != columns.data.len() {
+        return None;
+    }
+    let num_rows = state.get(0, rows_field)?.as_numeric()?;
+    let num_rows = num_rows.get::<f64>(&[0]).ok()? as usize;
+    if columns
+        .data
+        .iter()
+        .any(|column| column.size().first() != Some(&num_rows))
+    {
+        return None;
+    }
+    let row_names = match state.get(0, "rownames") {
+        Some(names) if !timetable => text_list(names)?,
+        _ => vec![],
+    };
+    //无法还原的行时间不影响读取各列
+    let row_times = match state.get(0, "rowTimes") {
+        _ if !timetable => None,
+        Some(MatValue::DateTime(times)) if times.size().first() == Some(&num_rows) => {
+            take_property(state, "rowTimes")
+        }
+        Some(MatValue::Duration(times)) if times.size().first() == Some(&num_rows) => {
+            take_property(state, "rowTimes")
+        }
+        Some(MatValue::Struct(times)) => regular_row_times(times, num_rows),
+        _ => None,
+    };
+    let columns = match take_property(state, "data")? {
+        MatValue::Cell(cell) => cell.data,
+        _ => return None,
+    };
+    Some(Table {
+        name,
+        size: vec![num_rows, columns.len()],
+        variable_names,
+        columns,
+        num_rows,
+        row_names,
+        row_times: row_times.map(Box::new),
+        timetable,
+    })
+}
+
+//规则采样的timetable只保存起点origin和步长stepSize(或采样率sampleRate),
+//展开为 origin + k*step, k = 0..num_rows
+fn regular_row_times(times: &StructArray, num_rows: usize) -> Option<MatValue> {
+    let step = match times.get(0, "stepSize") {
+        Some(MatValue::Duration(step)) => (*step.data.first()?)?,
+        _ => {
+            let rate = times.get(0, "sampleRate")?.as_numeric()?;
+            let rate = rate.get::<f64>(&[0]).ok()?;
+            if !rate.is_finite() || rate <= 0.0 {
+                return None;
+            }
+            Duration::nanoseconds((1e9 / rate).round() as i64)
+        }
+    };
+    let offsets = (0..num_rows).map(|k| step.checked_mul(i32::try_from(k).ok()?));
+    let size = vec![num_rows, 1];
+    match times.get(0, "origin")? {
+        MatValue::DateTime(origin) => {
+            let start = (*origin.data.first()?)?;
+            let data = offsets
+                .map(|offset| Some(Some(start.checked_add_signed(offset?)?)))
+                .collect::<Option<_>>()?;
+            Some(MatValue::DateTime(DateTimeArray {
+                name: String::new(),
+                size,
+                data,
+                time_zone: origin.time_zone.clone(),
+                format: origin.format.clone(),
+            }))
+        }
+        MatValue::Duration(origin) => {
+            let start = (*origin.data.first()?)?;
+            let data = offsets
+                .map(|offset| Some(Some(start.checked_add(&offset?)?)))
+                .collect::<Option<_>>()?;
+            Some(MatValue::Duration(DurationArray {
+                name: String::new(),
+                size,
+                data,
+                format: origin.format.clone(),
+            }))
+        }
+        _ => None,
+    }
+}
+
+//table的状态直接保存为对象属性,timetable等较新的类保存在名为any的1x1结构体中
+fn table_state(array: &mut StructArray) -> Option<&mut StructArray> {
+    if array.field_names.iter().any(|field| field == "data") {
+        return Some(array);
+    }
+    let position = array.field_names.iter().position(|name| name == "any")?;
+    match array.data.get_mut(position)? {
+        MatValue::Struct(state) if state.get_num_elements() == 1 => Some(state),
+        _ => None,
+    }
+}
+
+//codes按类别个数保存为uint8、uint16等整数,0表示未定义
+fn decode_categorical(object: &ObjectArray) -> Option<Categorical> {
+    let codes = object.data.get(0, "codes")?.as_numeric()?;
+    let categories = text_list(object.data.get(0, "categoryNames")?)?;
+    let ordinal = match object.data.get(0, "isOrdinal") {
+        Some(MatValue::Logical(flag)) => flag.data().first().copied().unwrap_or(false),
+        Some(MatValue::Numeric(flag)) => flag.get::<f64>(&[0]).is_ok_and(|flag| flag != 0.0),
+        _ => false,
+    };
+    let size = codes.size().clone();
+    let codes = codes
+        .cast(MatlabType::UInt32)
+        .ok()?
+        .as_slice::<u32>()
+        .ok()?
+        .to_vec();
+    if codes.iter().any(|&code| code as usize > categories.len()) {
+        return None;
+    }
+    Some(Categorical {
+        name: object.name().to_string(),
+        size,
+        codes,
+        categories,
+        ordinal,
+    })
+}
+
+//由char组成的cell,空矩阵表示没有元素
+fn text_list(value: &MatValue) -> Option<Vec<String>> {
+    match value {
+        MatValue::Cell(cell) => cell
+            .data
+            .iter()
+            .map(|text| text.as_char()?.as_string())
+            .collect(),
+        MatValue::Numeric(array) if array.get_num_elements() == 0 => Some(vec![]),
+        _ => None,
+    }
+}
+
+fn take_property(array: &mut StructArray, field: &str) -> Option<MatValue> {
+    let position = array.field_names.iter().position(|name| name == field)?;
+    let value = array.data.get_mut(position)?;
+    let empty = MatValue::Numeric(Array::double("", vec![0, 0], vec![]));
+    Some(std::mem::replace(value, empty))
+}
+
+fn text_property(object: &ObjectArray, field: &str) -> Option<String> {
+    object.data.get(0, field)?.as_char()?.as_string()
+}
+
+//引用:标记、维数个数、各维大小、对象编号,最后是类编号
+fn read_reference(reference: &Array) -> Option<(Vec<usize>, &[u32], usize)> {
+    let words = reference.as_slice::<u32>().ok()?;
+    let (&marker, words) = words.split_first()?;
+    let (&ndims, words) = words.split_first()?;
+    let ndims = ndims as usize;
+    if marker != REFERENCE_MARKER || ndims < 2 || words.len() < ndims {
+        return None;
+    }
+    let (dims, words) = words.split_at(ndims);
+    let size: Vec<usize> = dims.iter().map(|&d| d as usize).collect();
+    let num_objects = size
+        .iter()
+        .try_fold(1usize, |product, &d| product.checked_mul(d))?;
+    if words.len() != num_objects.checked_add(1)? {
+        return None;
+    }
+    let (ids, class_id) = words.split_at(num_objects);
+    Some((size, ids, class_id[0] as usize))
+}
+
+/// Size of the object array an MCOS reference points to.
+pub(crate) fn reference_size(reference: &Array) -> Option<Vec<usize>> {
+    read_reference(reference).map(|(size, _, _)| size)
+}
+
+//属性块:属性个数,之后每个属性为名称索引、类型、值,按8字节对齐
+fn read_property_blocks(
+    metadata: &[u8],
+    start: usize,
+    end: usize,
+    endianness: Endianness,
+) -> Option<Vec<Vec<Property>>> {
+    let word = |offset: usize| read_u32(metadata, offset, endianness);
+    let mut blocks = vec![];
+    let mut offset = start;
+    while offset < end {
+        let count = word(offset)?;
+        let properties = (0..count)
+            .map(|i| {
+                let entry = offset + 4 + 12 * i;
+                Some(Property {
+                    name_index: word(entry)?,
+                    kind: word(entry + 4)?,
+                    value: word(entry + 8)?,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        blocks.push(properties);
+        offset = (offset + 4 + 12 * count).next_multiple_of(8);
+    }
+    Some(blocks)
+}
+
+//最后一个单元是按类编号排列的1x1结构体,保存各类的默认属性值
+fn read_defaults(element: parse::DataElement) -> Result<Vec<Vec<(String, MatValue)>>, MatError> {
+    let cell = match MatValue::try_from(element)? {
+        MatValue::Cell(cell) => cell,
+        _ => return Ok(vec![]),
+    };
+    let defaults = cell
+        .data
+        .into_iter()
+        .map(|value| match value {
+            MatValue::Struct(array) if array.get_num_elements() == 1 => {
+                array.field_names.into_iter().zip(array.data).collect()
+            }
+            _ => vec![],
+        })
+        .collect();
+    Ok(defaults)
+}
+
+//子系统数据保存在一个结构体的MCOS字段中,类名为FileWrapper__的opaque里
+fn find_file_wrapper(element: parse::DataElement) -> Option<Vec<parse::DataElement>> {
+    match element {
+        parse::DataElement::OpaqueMatrix(_, _, class_name, data)
+            if class_name == "FileWrapper__" =>
+        {
+            match *data {
+                parse::DataElement::CellMatrix(_, _, _, cells) => Some(cells),
+                _ => None,
+            }
+        }
+        parse::DataElement::CellMatrix(_, _, _, elements)
+        | parse::DataElement::StructMatrix(_, _, _, _, elements) => {
+            elements.into_iter().find_map(find_file_wrapper)
+        }
+        _ => None,
+    }
+}
+
+fn to_bytes(data: parse::NumericData) -> Result<Vec<u8>, MatError> {
+    let data = NumericData::try_from(MatlabType::UInt8, data, None)?;
+    let (bytes, _) = u8::into_vecs(data).map_err(|_| MatError::InternalError)?;
+    Ok(bytes)
+}
+
+fn read_u32(bytes: &[u8], offset: usize, endianness: Endianness) -> Option<usize> {
+    let word: [u8; 4] = bytes.get(offset..offset + 4)?.try_into().ok()?;
+    let value = match endianness {
+        Endianness::Big => u32::from_be_bytes(word),
+        _ => u32::from_le_bytes(word),
+    };
+    Some(value as usize)
+}
+
+fn invalid_metadata() -> MatError {
+    MatError::ParseError(nom::Err::Failure(nom::error::Error {
+        input: &[],
+        code: nom::error::ErrorKind::Verify,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_fixtures::{matrix, subelement};
+    use crate::{MatFile, MatValue};
+
+    fn double(name: &str, values: &[f64]) -> Vec<u8> {
+        let data: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let dims = [values.len().min(1) as i32, values.len() as i32];
+        matrix(6, Some(&dims), name, &subelement(9, &data))
+    }
+    fn complex_double(values: &[(f64, f64)]) -> Vec<u8> {
+        let real: Vec<u8> = values.iter().flat_map(|v| v.0.to_le_bytes()).collect();
+        let imag: Vec<u8> = values.iter().flat_map(|v| v.1.to_le_bytes()).collect();
+        let dims: Vec<u8> = [1, values.len() as i32]
+            .iter()
+            .flat_map(|d| d.to_le_bytes())
+            .collect();
+        //标志字节中的0x08表示复数
+        let mut body = subelement(6, &[6, 0x08, 0, 0, 0, 0, 0, 0]);
+        body.extend(subelement(5, &dims));
+        body.extend(subelement(1, b""));
+        body.extend(subelement(9, &real));
+        body.extend(subelement(9, &imag));
+        subelement(14, &body)
+    }
+    //把二维矩阵元素的两个维数对调,得到列向量
+    fn column(mut element: Vec<u8>) -> Vec<u8> {
+        element[32..40].rotate_left(4);
+        element
+    }
+    fn logical(value: bool) -> Vec<u8> {
+        //标志字节中的0x02表示逻辑类型
+        let mut body = subelement(6, &[9, 0x02, 0, 0, 0, 0, 0, 0]);
+        body.extend(subelement(5, &[1, 0, 0, 0, 1, 0, 0, 0]));
+        body.extend(subelement(1, b""));
+        body.extend(subelement(2, &[value as u8]));
+        subelement(14, &body)
+    }
+    fn chars(text: &str) -> Vec<u8> {
+        let dims = [1, text.len() as i32];
+        matrix(4, Some(&dims), "", &subelement(16, text.as_bytes()))
+    }
+    fn uint8(values: &[u8]) -> Vec<u8> {
+        matrix(
+            9,
+            Some(&[values.len() as i32, 1]),
+            "",
+            &subelement(2, values),
+        )
+    }
+    fn uint32(values: &[u32]) -> Vec<u8> {
+        let data: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        matrix(
+            13,
+            Some(&[values.len() as i32, 1]),
+            "",
+            &subelement(6, &data),
+        )
+    }
+    fn cell(name: &str, elements: &[Vec<u8>]) -> Vec<u8> {
+        let dims = [1, elements.len() as i32];
+        matrix(1, Some(&dims), name, &elements.concat())
+    }
+    fn struct_(fields: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut rest = subelement(5, &32i32.to_le_bytes());
+        let mut names = vec![0u8; 32 * fields.len()];
+        for (i, (field, _)) in fields.iter().enumerate() {
+            names[32 * i..32 * i + field.len()].copy_from_slice(field.as_bytes());
+        }
+        rest.extend(subelement(1, &names));
+        for (_, value) in fields {
+            rest.extend(value);
+        }
+        matrix(2, Some(&[1, 1]), "", &rest)
+    }
+    fn opaque(name: &str, class_name: &str, nested: &[u8]) -> Vec<u8> {
+        let mut rest = subelement(1, b"MCOS");
+        rest.extend(subelement(1, class_name.as_bytes()));
+        rest.extend(nested);
+        matrix(17, None, name, &rest)
+    }
+    fn reference(ids: &[u32], class_id: u32) -> Vec<u32> {
+        let mut words = vec![0xDD00_0000, 2, 1, ids.len() as u32];
+        words.extend(ids);
+        words.push(class_id);
+        words
+    }
+    fn words(values: &[u32]) -> Vec<u8> {
+        let mut bytes: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        bytes.resize(bytes.len().next_multiple_of(8), 0);
+        bytes
+    }
+    //每个区域的第一项都是空的占位项
+    fn metadata(
+        names: &[&str],
+        classes: &[[u32; 2]],
+        saveobj: &[&[[u32; 3]]],
+        objects: &[[u32; 3]],
+        normobj: &[&[[u32; 3]]],
+    ) -> Vec<u8> {
+        let mut text = names.join("\0").into_bytes();
+        text.push(0);
+        text.resize(text.len().next_multiple_of(8), 0);
+        let blocks = |blocks: &[&[[u32; 3]]]| {
+            let mut bytes = words(&[0]);
+            for block in blocks {
+                let mut block_words = vec![block.len() as u32];
+                block_words.extend(block.iter().flatten());
+                bytes.extend(words(&block_words));
+            }
+            bytes
+        };
+        let mut class_words = vec![0; 4];
+        for [package, class] in classes {
+            class_words.extend([*package, *class, 0, 0]);
+        }
+        let mut object_words = vec![0; 6];
+        for (i, [class_id, saveobj_id, normobj_id]) in objects.iter().enumerate() {
+            object_words.extend([*class_id, 0, 0, *saveobj_id, *normobj_id, i as u32 + 1]);
+        }
+        let regions = [
+            words(&class_words),
+            blocks(saveobj),
+            words(&object_words),
+            blocks(normobj),
+        ];
+        let mut offsets = vec![40 + text.len() as u32];
+        for region in &regions {
+            offsets.push(offsets.last().unwrap() + region.len() as u32);
+        }
+        offsets.resize(8, *offsets.last().unwrap());
+        let mut bytes = words(&[4, names.len() as u32]);
+        bytes.extend(words(&offsets));
+        bytes.extend(text);
+        bytes.extend(regions.concat());
+        bytes
+    }
+    fn mat_file(variables: &[Vec<u8>], file_wrapper: &[Vec<u8>]) -> Vec<u8> {
+        let mut subsystem = vec![0x00, 0x01, b'I', b'M', 0, 0, 0, 0];
+        subsystem.extend(struct_(&[(
+            "MCOS",
+            opaque("", "FileWrapper__", &cell("", file_wrapper)),
+        )]));
+        crate::test_fixtures::mat_file(variables, Some(&subsystem))
+    }
+
+    #[test]
+    fn decode_string_array() -> Result<(), crate::mat_error::MatError> {
+        let metadata = metadata(
+            &["any", "string"],
+            &[[0, 2]],
+            &[],
+            &[[1, 0, 1]],
+            &[&[[1, 1, 0]]],
+        );
+        //2x2: ["ab", <missing>; "", "µx"]
+        let chars: Vec<u16> = "abµx".encode_utf16().collect();
+        let mut any = vec![1, 2, 2, 2, 2, 0, u64::MAX, 2];
+        any.extend(chars.chunks(4).map(|chunk| {
+            let mut word = [0u8; 8];
+            for (i, c) in chunk.iter().enumerate() {
+                word[2 * i..2 * i + 2].copy_from_slice(&c.to_le_bytes());
+            }
+            u64::from_le_bytes(word)
+        }));
+        let any: Vec<u8> = any.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let dims = [any.len() as i32 / 8, 1];
+        let file_wrapper = [
+            uint8(&metadata),
+            double("", &[]),
+            matrix(15, Some(&dims), "", &subelement(13, &any)),
+            cell("", &[double("", &[]), double("", &[])]),
+        ];
+        let variables = [opaque("s", "string", &uint32(&reference(&[1], 1)))];
+        let file = MatFile::parse(mat_file(&variables, &file_wrapper).as_slice())?;
+        let strings = file.find_by_name("s").unwrap().as_string_array().unwrap();
+        assert_eq!(strings.name(), "s");
+        assert_eq!(strings.size(), &vec![2, 2]);
+        assert_eq!(
+            strings.iter().collect::<Vec<_>>(),
+            vec![Some("ab"), Some(""), None, Some("µx")]
+        );
+        assert_eq!(strings.get_at(&[0, 1]), Some(None));
+        assert_eq!(strings.get_at(&[1, 1]), Some(Some("µx")));
+        Ok(())
+    }
+
+    #[test]
+    fn decode_datetime_and_duration() -> Result<(), crate::mat_error::MatError> {
+        use chrono::{Duration, NaiveDate};
+
+        let names = ["data", "tz", "fmt", "datetime", "millis", "duration"];
+        let metadata = metadata(
+            &names,
+            &[[0, 4], [0, 6]],
+            &[],
+            &[[1, 0, 1], [2, 0, 2]],
+            &[&[[1, 1, 0], [2, 1, 1], [3, 1, 2]], &[[5, 1, 3], [3, 1, 4]]],
+        );
+        let file_wrapper = [
+            uint8(&metadata),
+            double("", &[]),
+            complex_double(&[(86_400_000.0, 0.25), (f64::NAN, 0.0), (-1.0, 0.0)]),
+            chars("+02:00"),
+            chars("yyyy-MM-dd"),
+            double("", &[1500.0, f64::NAN]),
+            chars("hh:mm:ss"),
+            cell("", &[double("", &[]), double("", &[]), double("", &[])]),
+        ];
+        let variables = [
+            opaque("t", "datetime", &uint32(&reference(&[1], 1))),
+            opaque("d", "duration", &uint32(&reference(&[2], 2))),
+        ];
+        let file = MatFile::parse(mat_file(&variables, &file_wrapper).as_slice())?;
+
+        let times = file.find_by_name("t").unwrap().as_datetime().unwrap();
+        assert_eq!(times.size(), &vec![1, 3]);
+        assert_eq!(times.time_zone(), Some("+02:00"));
+        assert_eq!(times.format(), "yyyy-MM-dd");
+        let day = NaiveDate::from_ymd_opt(1970, 1, 2).unwrap();
+        assert_eq!(
+            times.data(),
+            &[
+                Some(day.and_hms_micro_opt(0, 0, 0, 250).unwrap()),
+                None,
+                Some(
+                    NaiveDate::from_ymd_opt(1969, 12, 31)
+                        .unwrap()
+                        .and_hms_milli_opt(23, 59, 59, 999)
+                        .unwrap()
+                ),
+            ]
+        );
+        let local = times.to_fixed_offset().unwrap();
+        assert_eq!(
+            local[0].unwrap().naive_local(),
+            day.and_hms_micro_opt(2, 0, 0, 250).unwrap()
+        );
+
+        let durations = file.find_by_name("d").unwrap().as_duration().unwrap();
+        assert_eq!(durations.format(), "hh:mm:ss");
+        assert_eq!(
+            durations.data(),
+            &[Some(Duration::milliseconds(1500)), None]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn decode_table_and_timetable() -> Result<(), crate::mat_error::MatError> {
+        let names = [
+            "data",
+            "varnames",
+            "nrows",
+            "rownames",
+            "table",
+            "varNames",
+            "numRows",
+            "rowTimes",
+            "timetable",
+            "millis",
+            "duration",
+            "any",
+            "origin",
+        ];
+        let metadata = metadata(
+            &names,
+            &[[0, 5], [0, 9], [0, 11]],
+            &[],
+            &[
+                [1, 0, 1],
+                [2, 0, 2],
+                [3, 0, 3],
+                [2, 0, 4],
+                [1, 0, 5],
+                [3, 0, 6],
+                [3, 0, 7],
+                [2, 0, 8],
+            ],
+            &[
+                &[[1, 1, 0], [2, 1, 1], [3, 1, 2], [4, 1, 3]],
+                &[[12, 1, 4]],
+                &[[10, 1, 5]],
+                &[[12, 1, 6]],
+                &[[1, 1, 7], [2, 1, 8], [3, 1, 2]],
+                &[[10, 1, 9]],
+                &[[10, 1, 10]],
+                &[[12, 1, 11]],
+            ],
+        );
+        let timetable_state = |row_times: Vec<u8>| {
+            struct_(&[
+                ("data", cell("", &[column(double("", &[5.0, 6.0]))])),
+                ("varNames", cell("", &[chars("v")])),
+                ("numRows", double("", &[2.0])),
+                ("rowTimes", row_times),
+            ])
+        };
+        let file_wrapper = [
+            uint8(&metadata),
+            double("", &[]),
+            cell(
+                "",
+                &[
+                    column(double("", &[1.0, 2.0])),
+                    column(cell("", &[chars("x"), chars("y")])),
+                ],
+            ),
+            cell("", &[chars("a"), chars("b")]),
+            double("", &[2.0]),
+            cell("", &[chars("r1"), chars("r2")]),
+            //较新的版本把timetable的状态放在any结构体中
+            timetable_state(uint32(&reference(&[3], 3))),
+            column(double("", &[1000.0, 2000.0])),
+            //规则采样的timetable只保存起点和步长
+            timetable_state(struct_(&[
+                ("origin", uint32(&reference(&[6], 3))),
+                ("stepSize", uint32(&reference(&[7], 3))),
+            ])),
+            cell("", &[column(double("", &[1.0, 2.0, 3.0]))]),
+            cell("", &[chars("a")]),
+            double("", &[500.0]),
+            double("", &[250.0]),
+            timetable_state(struct_(&[("origin", double("", &[0.0]))])),
+            cell("", &vec![double("", &[]); 4]),
+        ];
+        let variables = [
+            opaque("t", "table", &uint32(&reference(&[1], 1))),
+            opaque("tt", "timetable", &uint32(&reference(&[2], 2))),
+            opaque("regular", "timetable", &uint32(&reference(&[4], 2))),
+            opaque("bad", "table", &uint32(&reference(&[5], 1))),
+            opaque("unknown", "timetable", &uint32(&reference(&[8], 2))),
+        ];
+        let file = MatFile::parse(mat_file(&variables, &file_wrapper).as_slice())?;
+
+        let table = file.find_by_name("t").unwrap().as_table().unwrap();
+        assert!(!table.is_timetable());
+        assert_eq!(table.size(), &vec![2, 2]);
+        assert_eq!(table.variable_names(), &["a", "b"]);
+        assert_eq!(table.row_names(), &["r1", "r2"]);
+        let a = table.column("a").unwrap().as_numeric().unwrap();
+        assert_eq!(a.get::<f64>(&[1])?, 2.0);
+        let b = table.column("b").unwrap().as_cell().unwrap();
+        assert_eq!(b.data()[1].as_char().unwrap().as_string().unwrap(), "y");
+
+        let timetable = file.find_by_name("tt").unwrap().as_table().unwrap();
+        assert!(timetable.is_timetable());
+        assert_eq!(timetable.num_rows(), 2);
+        assert_eq!(timetable.variable_names(), &["v"]);
+        assert!(timetable.row_names().is_empty());
+        let times = timetable.row_times().unwrap().as_duration().unwrap();
+        assert_eq!(times.data()[1], Some(chrono::Duration::seconds(2)));
+
+        let regular = file.find_by_name("regular").unwrap().as_table().unwrap();
+        assert!(regular.is_timetable());
+        let times = regular.row_times().unwrap().as_duration().unwrap();
+        assert_eq!(times.size(), &vec![2, 1]);
+        assert_eq!(
+            times.data(),
+            &[
+                Some(chrono::Duration::milliseconds(500)),
+                Some(chrono::Duration::milliseconds(750))
+            ]
+        );
+        //无法展开的行时间只是缺失,各列照常读取
+        let unknown = file.find_by_name("unknown").unwrap().as_table().unwrap();
+        assert!(unknown.is_timetable());
+        assert!(unknown.row_times().is_none());
+        assert_eq!(unknown.column("v").unwrap().size(), &vec![2, 1]);
+        //列的行数与nrows不一致
+        let bad = file.find_by_name("bad").unwrap().as_object().unwrap();
+        assert_eq!(bad.class_name(), "table");
+        assert_eq!(
+            bad.as_struct()
+                .get(0, "data")
+                .unwrap()
+                .as_cell()
+                .unwrap()
+                .data()
+                .len(),
+            1
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn decode_categorical() -> Result<(), crate::mat_error::MatError> {
+        let names = ["codes", "categoryNames", "isOrdinal", "categorical"];
+        let metadata = metadata(
+            &names,
+            &[[0, 4]],
+            &[],
+            &[[1, 0, 1]],
+            &[&[[1, 1, 0], [2, 1, 1], [3, 1, 2]]],
+        );
+        let file_wrapper = [
+            uint8(&metadata),
+            double("", &[]),
+            uint8(&[1, 0, 2, 1]),
+            cell("", &[chars("low"), chars("high")]),
+            logical(true),
+            cell("", &[double("", &[]), double("", &[])]),
+        ];
+        let variables = [opaque("level", "categorical", &uint32(&reference(&[1], 1)))];
+        let file = MatFile::parse(mat_file(&variables, &file_wrapper).as_slice())?;
+
+        let levels = file
+            .find_by_name("level")
+            .unwrap()
+            .as_categorical()
+            .unwrap();
+        assert_eq!(levels.size(), &vec![4, 1]);
+        assert!(levels.is_ordinal());
+        assert_eq!(levels.categories(), &["low", "high"]);
+        assert_eq!(levels.codes(), &[1, 0, 2, 1]);
+        assert_eq!(
+            levels.iter().collect::<Vec<_>>(),
+            vec![Some("low"), None, Some("high"), Some("low")]
+        );
+        assert_eq!(levels.get(2), Some(Some("high")));
+        assert_eq!(levels.get(4), None);
+        Ok(())
+    }
+
+    #[test]
+    fn resolve_mcos_objects() -> Result<(), crate::mat_error::MatError> {
+        let names = ["x", "y", "label", "geom", "Point", "Line", "start", "self"];
+        let metadata = metadata(
+            &names,
+            &[[4, 5], [0, 6]],
+            &[&[[1, 1, 0], [2, 2, 4], [3, 0, 5]]],
+            &[[1, 0, 1], [1, 1, 0], [2, 0, 2]],
+            &[&[[1, 1, 0], [2, 2, 3]], &[[7, 1, 1], [8, 1, 2]]],
+        );
+        let file_wrapper = [
+            uint8(&metadata),
+            double("", &[]),
+            double("", &[1.5]),
+            uint32(&reference(&[1], 1)),
+            uint32(&reference(&[3], 2)),
+            cell(
+                "",
+                &[
+                    double("", &[]),
+                    struct_(&[(
+                        "label",
+                        matrix(4, Some(&[1, 4]), "", &subelement(16, b"none")),
+                    )]),
+                    double("", &[]),
+                ],
+            ),
+        ];
+        let variables = [
+            double("a", &[2.0]),
+            opaque("p", "geom.Point", &uint32(&reference(&[1, 2], 1))),
+            cell("c", &[opaque("", "Line", &uint32(&reference(&[3], 2)))]),
+        ];
+        let file = MatFile::parse(mat_file(&variables, &file_wrapper).as_slice())?;
+        assert_eq!(file.names().collect::<Vec<_>>(), vec!["a", "p", "c"]);
+
+        let number = |value: &MatValue| value.as_numeric().unwrap().get::<f64>(&[0]).unwrap();
+        let text = |value: &MatValue| value.as_char().unwrap().as_string().unwrap();
+        let points = file.find_by_name("p").unwrap().as_object().unwrap();
+        assert_eq!(points.class_name(), "geom.Point");
+        assert_eq!(points.size(), &vec![1, 2]);
+        let points = points.as_struct();
+        assert_eq!(points.fields(), &["label", "x", "y"]);
+        assert_eq!(number(points.get(0, "x").unwrap()), 1.5);
+        assert_eq!(number(points.get(0, "y").unwrap()), 3.0);
+        assert_eq!(text(points.get(0, "label").unwrap()), "none");
+        assert_eq!(number(points.get(1, "y").unwrap()), 4.0);
+        assert_eq!(text(points.get(1, "label").unwrap()), "Point");
+
+        let line = file.find_by_name("c").unwrap().as_cell().unwrap().data()[0]
+            .as_object()
+            .unwrap();
+        assert_eq!(line.class_name(), "Line");
+        let start = line
+            .as_struct()
+            .get(0, "start")
+            .unwrap()
+            .as_object()
+            .unwrap();
+        assert_eq!(start.class_name(), "geom.Point");
+        assert_eq!(number(start.as_struct().get(0, "x").unwrap()), 1.5);
+        //自引用无法展开,保留原始的引用数组
+        let cycle = line
+            .as_struct()
+            .get(0, "self")
+            .unwrap()
+            .as_numeric()
+            .unwrap();
+        assert_eq!(cycle.get::<u32>(&[0])?, 0xDD00_0000);
+        Ok(())
+    }
+
+    #[test]
+    fn malformed_subsystem_keeps_other_variables() -> Result<(), crate::mat_error::MatError> {
+        //元数据被截断,子系统无法解析,对象保持为Opaque
+        let file_wrapper = [uint8(&[4, 0, 0, 0, 1]), double("", &[])];
+        let variables = [
+            double("a", &[2.0]),
+            opaque("s", "string", &uint32(&reference(&[1], 1))),
+        ];
+        let file = MatFile::parse(mat_file(&variables, &file_wrapper).as_slice())?;
+        let a = file.find_by_name("a").unwrap().as_numeric().unwrap();
+        assert_eq!(a.get::<f64>(&[0])?, 2.0);
+        let s = file.find_by_name("s").unwrap().as_opaque().unwrap();
+        assert_eq!(s.class_name(), "string");
+        assert_eq!(s.size(), &vec![1, 1]);
+        Ok(())
+    }
+
+    //tests/fixtures/mcos.mat由同目录下的make_mcos.m在MATLAB中保存
+    #[test]
+    #[ignore = "需要先用MATLAB运行tests/fixtures/make_mcos.m生成mcos.mat"]
+    fn read_matlab_saved_objects() -> Result<(), crate::mat_error::MatError> {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/mcos.mat");
+        let file = MatFile::parse(std::fs::File::open(path)?)?;
+        let value = |name: &str| file.find_by_name(name).unwrap();
+
+        let s = value("s").as_string_array().unwrap();
+        assert_eq!(s.size(), &vec![2, 2]);
+        assert_eq!(
+            s.iter().collect::<Vec<_>>(),
+            vec![Some("ab"), Some(""), None, Some("\u{b5}x")]
+        );
+
+        let d = value("d").as_datetime().unwrap();
+        assert_eq!(d.time_zone(), Some("UTC"));
+        let first = chrono::NaiveDate::from_ymd_opt(2020, 1, 2)
+            .unwrap()
+            .and_hms_opt(3, 4, 5)
+            .unwrap();
+        assert_eq!(
+            d.data(),
+            &[Some(first), Some(first + chrono::Duration::days(1)), None]
+        );
+
+        let dur = value("dur").as_duration().unwrap();
+        assert_eq!(dur.size(), &vec![2, 1]);
+        assert_eq!(dur.data()[0], Some(chrono::Duration::milliseconds(1500)));
+
+        let t = value("t").as_table().unwrap();
+        assert_eq!(t.size(), &vec![2, 2]);
+        assert_eq!(t.variable_names(), &["a", "b"]);
+        assert_eq!(t.row_names(), &["r1", "r2"]);
+        let a = t.column("a").unwrap().as_numeric().unwrap();
+        assert_eq!(a.get::<f64>(&[1])?, 2.0);
+
+        let tt = value("tt").as_table().unwrap();
+        assert!(tt.is_timetable());
+        assert_eq!(tt.variable_names(), &["v"]);
+        let times = tt.row_times().unwrap().as_duration().unwrap();
+        assert_eq!(times.data()[1], Some(chrono::Duration::seconds(2)));
+
+        let c = value("c").as_categorical().unwrap();
+        assert!(c.is_ordinal());
+        assert_eq!(c.categories(), &["lo", "hi"]);
+        assert_eq!(
+            c.iter().collect::<Vec<_>>(),
+            vec![Some("lo"), Some("hi"), Some("lo"), None]
+        );
+
+        let plain = value("plain").as_numeric().unwrap();
+        assert_eq!(plain.as_slice::<f64>()?, &[1.0, 2.0, 3.0]);
+        Ok(())
+    }
+}
@@ -0,0 +1,124 @@
+//! Conversions between 2-D [`Array`]s and `nalgebra` dynamic matrices.
+//!
+//! Both store elements in column-major order, so the owned conversions move
+//! the element buffer instead of copying it.
+use nalg::{DMatrix, Scalar};
+use num_complex::Complex;
+
+use crate::mat_error::MatError;
+use crate::{Array, MatElement};
+
+impl<T: MatElement + Scalar> TryFrom<Array> for DMatrix<T> {
+    type Error = MatError;
+    /// Fails if the array is complex, not 2-D or its class is not `T`.
+    fn try_from(array: Array) -> Result<Self, Self::Error> {
+        let (rows, cols) = matrix_shape(&array)?;
+        match T::into_vecs(array.data) {
+            Ok((real, None)) => Ok(DMatrix::from_vec(rows, cols, real)),
+            Ok((_, Some(_))) => Err(MatError::ParamsError(format!(
+                "变量{}是复数矩阵,请转换为Complex类型",
+                array.name
+            ))),
+            Err(data) => Err(MatError::ClassMismatch {
+                expected: T::CLASS,
+                found: data.class(),
+            }),
+        }
+    }
+}
+
+impl<T: MatElement + Scalar> TryFrom<&Array> for DMatrix<T> {
+    type Error = MatError;
+    fn try_from(array: &Array) -> Result<Self, Self::Error> {
+        let (rows, cols) = matrix_shape(array)?;
+        match T::slices(&array.data) {
+            Some((real, None)) => Ok(DMatrix::from_column_slice(rows, cols, real)),
+            Some((_, Some(_))) => Err(MatError::ParamsError(format!(
+                "变量{}是复数矩阵,请转换为Complex类型",
+                array.name
+            ))),
+            None => Err(array.class_mismatch::<T>()),
+        }
+    }
+}
+
+impl<T: MatElement + Scalar> TryFrom<&Array> for DMatrix<Complex<T>> {
+    type Error = MatError;
+    /// Real arrays are accepted and get a zero imaginary part.
+    fn try_from(array: &Array) -> Result<Self, Self::Error> {
+        let (rows, cols) = matrix_shape(array)?;
+        Ok(DMatrix::from_iterator(
+            rows,
+            cols,
+            array.iter_complex::<T>()?,
+        ))
+    }
+}
+
+impl Array {
+    /// Builds an array from a `nalgebra` matrix, taking over its buffer.
+    pub fn from_dmatrix<T: MatElement + Scalar>(name: &str, matrix: DMatrix<T>) -> Array {
+        let size = vec![matrix.nrows(), matrix.ncols()];
+        let real: Vec<T> = matrix.data.into();
+        Array::from_data(name, size, T::into_data(real, None))
+    }
+    /// Builds a complex array from a `nalgebra` matrix of `Complex<T>`.
+    pub fn from_complex_dmatrix<T: MatElement + Scalar>(
+        name: &str,
+        matrix: &DMatrix<Complex<T>>,
+    ) -> Array {
+        let size = vec![matrix.nrows(), matrix.ncols()];
+        let real = matrix.iter().map(|c| c.re).collect();
+        let imag = matrix.iter().map(|c| c.im).collect();
+        Array::from_data(name, size, T::into_data(real, Some(imag)))
+    }
+}
+
+//允许末尾的单一维度,如 3x2x1
+fn matrix_shape(array: &Array) -> Result<(usize, usize), MatError> {
+    match array.size.as_slice() {
+        [rows, cols, rest @ ..] if rest.iter().all(|&d| d == 1) => Ok((*rows, *cols)),
+        _ => Err(MatError::ParamsError(format!(
+            "变量{}不是二维矩阵",
+            array.name
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalg::DMatrix;
+    use num_complex::Complex;
+
+    use crate::{Array, NumericData};
+
+    #[test]
+    fn dmatrix_round_trip() -> Result<(), crate::mat_error::MatError> {
+        let source = DMatrix::from_row_slice(2, 3, &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        let array = Array::from_dmatrix("m", source.clone());
+        assert_eq!(array.size(), &vec![2, 3]);
+        match array.data() {
+            NumericData::Double { real, imag: None } => {
+                assert_eq!(real, &vec![1.0, 4.0, 2.0, 5.0, 3.0, 6.0])
+            }
+            _ => panic!("expected a real double array"),
+        }
+        assert_eq!(DMatrix::<f64>::try_from(&array)?, source);
+        assert!(DMatrix::<f32>::try_from(&array).is_err());
+        assert_eq!(DMatrix::<f64>::try_from(array)?, source);
+        Ok(())
+    }
+
+    #[test]
+    fn complex_dmatrix_round_trip() -> Result<(), crate::mat_error::MatError> {
+        let source = DMatrix::from_row_slice(1, 2, &[Complex::new(1i16, 2), Complex::new(3, -4)]);
+        let array = Array::from_complex_dmatrix("z", &source);
+        assert!(DMatrix::<i16>::try_from(&array).is_err());
+        assert_eq!(DMatrix::<Complex<i16>>::try_from(&array)?, source);
+
+        let real = Array::from_dmatrix("r", DMatrix::from_element(2, 1, 7u8));
+        let complex = DMatrix::<Complex<u8>>::try_from(&real)?;
+        assert_eq!(complex[(1, 0)], Complex::new(7, 0));
+        Ok(())
+    }
+}
@@ -0,0 +1,169 @@
+//! Conversions between [`Array`] and `ndarray` arrays.
+//!
+//! MATLAB stores elements in column-major order, so arrays read from a file
+//! come back in Fortran layout and arrays written to a file are copied out in
+//! Fortran order, whatever their memory layout.
+use ndarr::{Array2, ArrayBase, ArrayD, ArrayViewD, Data, Dimension, Ix2, IxDyn, ShapeBuilder};
+use num_complex::Complex;
+
+use crate::mat_error::MatError;
+use crate::{matlab_size, Array, MatElement};
+
+impl<T: MatElement> TryFrom<&Array> for ArrayD<T> {
+    type Error = MatError;
+    /// Fails if the array is complex or its class is not `T`.
+    fn try_from(array: &Array) -> Result<Self, Self::Error> {
+        match T::slices(&array.data) {
+            Some((real, None)) => from_column_major(&array.size, real.to_vec()),
+            Some((_, Some(_))) => Err(MatError::ParamsError(format!(
+                "变量{}是复数矩阵,请转换为Complex类型",
+                array.name
+            ))),
+            None => Err(array.class_mismatch::<T>()),
+        }
+    }
+}
+
+impl<T: MatElement> TryFrom<&Array> for Array2<T> {
+    type Error = MatError;
+    fn try_from(array: &Array) -> Result<Self, Self::Error> {
+        ArrayD::<T>::try_from(array)?
+            .into_dimensionality::<Ix2>()
+            .map_err(|_| MatError::ParamsError(format!("变量{}不是二维矩阵", array.name)))
+    }
+}
+
+impl<T: MatElement> TryFrom<&Array> for ArrayD<Complex<T>> {
+    type Error = MatError;
+    /// Real arrays are accepted and get a zero imaginary part.
+    fn try_from(array: &Array) -> Result<Self, Self::Error> {
+        let data = array.iter_complex::<T>()?.collect();
+        from_column_major(&array.size, data)
+    }
+}
+
+impl<T: MatElement> TryFrom<&Array> for Array2<Complex<T>> {
+    type Error = MatError;
+    fn try_from(array: &Array) -> Result<Self, Self::Error> {
+        ArrayD::<Complex<T>>::try_from(array)?
+            .into_dimensionality::<Ix2>()
+            .map_err(|_| MatError::ParamsError(format!("变量{}不是二维矩阵", array.name)))
+    }
+}
+
+impl Array {
+    /// Borrows the real part as an `ndarray` view with Fortran-order strides, without copying.
+    ///
+    /// Fails if the class of the array is not `T`.
+    pub fn view<T: MatElement>(&self) -> Result<ArrayViewD<'_, T>, MatError> {
+        let (real, _) = T::slices(&self.data).ok_or_else(|| self.class_mismatch::<T>())?;
+        view_column_major(&self.size, real)
+    }
+    /// Borrows the imaginary part of a complex array, like [`Array::view`].
+    pub fn view_imag<T: MatElement>(&self) -> Result<Option<ArrayViewD<'_, T>>, MatError> {
+        match T::slices(&self.data).ok_or_else(|| self.class_mismatch::<T>())? {
+            (_, Some(imag)) => view_column_major(&self.size, imag).map(Some),
+            (_, None) => Ok(None),
+        }
+    }
+    /// Builds an array from any `ndarray` array.
+    ///
+    /// One-dimensional arrays become 1xN row vectors and zero-dimensional
+    /// arrays become 1x1 scalars.
+    pub fn from_ndarray<T, S, D>(name: &str, array: &ArrayBase<S, D>) -> Array
+    where
+        T: MatElement,
+        S: Data<Elem = T>,
+        D: Dimension,
+    {
+        let real = array.t().iter().copied().collect();
+        Array::from_data(name, matlab_size(array.shape()), T::into_data(real, None))
+    }
+    /// Builds a complex array from any `ndarray` array of `Complex<T>`.
+    pub fn from_complex_ndarray<T, S, D>(name: &str, array: &ArrayBase<S, D>) -> Array
+    where
+        T: MatElement,
+        S: Data<Elem = Complex<T>>,
+        D: Dimension,
+    {
+        let real = array.t().iter().map(|c| c.re).collect();
+        let imag = array.t().iter().map(|c| c.im).collect();
+        Array::from_data(
+            name,
+            matlab_size(array.shape()),
+            T::into_data(real, Some(imag)),
+        )
+    }
+}
+
+fn from_column_major<T>(size: &[usize], data: Vec<T>) -> Result<ArrayD<T>, MatError> {
+    ArrayD::from_shape_vec(IxDyn(size).f(), data)
+        .map_err(|e| MatError::ParamsError(format!("矩阵维数与数据长度不一致: {}", e)))
+}
+
+fn view_column_major<'a, T>(size: &[usize], data: &'a [T]) -> Result<ArrayViewD<'a, T>, MatError> {
+    ArrayViewD::from_shape(IxDyn(size).f(), data)
+        .map_err(|e| MatError::ParamsError(format!("矩阵维数与数据长度不一致: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarr::{arr2, Array2, ArrayD, IxDyn, ShapeBuilder};
+    use num_complex::Complex;
+
+    use crate::{Array, NumericData};
+
+    #[test]
+    fn column_major_round_trip() -> Result<(), crate::mat_error::MatError> {
+        let source = arr2(&[[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+        let array = Array::from_ndarray("a", &source);
+        assert_eq!(array.size(), &vec![2, 3]);
+        match array.data() {
+            NumericData::Double { real, imag: None } => {
+                assert_eq!(real, &vec![1.0, 4.0, 2.0, 5.0, 3.0, 6.0])
+            }
+            _ => panic!("expected a real double array"),
+        }
+        assert_eq!(Array2::<f64>::try_from(&array)?, source);
+        assert!(ArrayD::<i32>::try_from(&array).is_err());
+
+        let fortran = ArrayD::from_shape_vec(IxDyn(&[2, 2, 2]).f(), (0..8i16).collect()).unwrap();
+        let array = Array::from_ndarray("b", &fortran);
+        assert_eq!(ArrayD::<i16>::try_from(&array)?, fortran);
+        assert!(Array2::<i16>::try_from(&array).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn complex_round_trip() -> Result<(), crate::mat_error::MatError> {
+        let source = arr2(&[[Complex::new(1.0f32, -1.0), Complex::new(2.0, 0.5)]]);
+        let array = Array::from_complex_ndarray("z", &source);
+        assert!(Array2::<f32>::try_from(&array).is_err());
+        assert_eq!(Array2::<Complex<f32>>::try_from(&array)?, source);
+
+        let real = Array::from_ndarray("r", &ndarr::arr1(&[1u8, 2]));
+        assert_eq!(real.size(), &vec![1, 2]);
+        let complex = Array2::<Complex<u8>>::try_from(&real)?;
+        assert_eq!(complex[[0, 1]], Complex::new(2, 0));
+        Ok(())
+    }
+
+    #[test]
+    fn view_borrows_data() -> Result<(), crate::mat_error::MatError> {
+        let source = arr2(&[[1, 2, 3], [4, 5, 6]]);
+        let array = Array::from_ndarray("v", &source);
+        let view = array.view::<i32>()?;
+        assert_eq!(view, source.into_dyn());
+        match array.data() {
+            NumericData::Int32 { real, .. } => assert_eq!(view.as_ptr(), real.as_ptr()),
+            _ => panic!("expected an int32 array"),
+        }
+        assert!(array.view::<f64>().is_err());
+        assert!(array.view_imag::<i32>()?.is_none());
+
+        let z = Array::from_complex_ndarray("z", &arr2(&[[Complex::new(1.0, 2.0)]]));
+        assert_eq!(z.view::<f64>()?[[0, 0]], 1.0);
+        assert_eq!(z.view_imag::<f64>()?.unwrap()[[0, 0]], 2.0);
+        Ok(())
+    }
+}
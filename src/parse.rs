@@ -11,7 +11,7 @@ use nom::combinator::value;
 use nom::multi::count;
 use nom::number::complete::{
     be_f32, be_f64, be_i16, be_i32, be_i64, be_i8, be_u16, be_u32, be_u64, be_u8, i32, le_f32,
-    le_f64, le_i16, le_i32, le_i64, le_i8, le_u16, le_u32, le_u64, le_u8, u32,
+    le_f64, le_i16, le_i32, le_i64, le_i8, le_u16, le_u32, le_u64, le_u8, u16, u32,
 };
 use nom::number::Endianness;
 use nom::sequence::tuple;
@@ -249,7 +249,7 @@ impl MatlabType {
             MatlabType::UInt8 => Some(DataType::UInt8),
             MatlabType::Int16 => Some(DataType::Int16),
             MatlabType::UInt16 => Some(DataType::UInt16),
-            MatlabType::Int32 => Some(DataType::UInt32),
+            MatlabType::Int32 => Some(DataType::Int32),
             MatlabType::UInt32 => Some(DataType::UInt32),
             MatlabType::Int64 => Some(DataType::Int64),
             MatlabType::UInt64 => Some(DataType::UInt64),
@@ -260,6 +260,10 @@ impl MatlabType {
 pub type Dimensions = Vec<i32>;
 pub type RowIndex = Vec<usize>;
 pub type ColumnShift = Vec<usize>;
+/// UTF-16 code units of a char array in column-major order.
+pub type CharData = Vec<u16>;
+pub type FieldNames = Vec<String>;
+pub type ClassName = String;
 
 #[derive(Clone, Debug)]
 pub enum DataElement {
@@ -279,10 +283,19 @@ pub enum DataElement {
         NumericData,
         Option<NumericData>,
     ),
-    // CharacterMatrix,
-    // Cell Matrix,
-    // Structure Matrix,
-    // Object Matrix,
+    CharMatrix(ArrayFlags, Dimensions, String, CharData),
+    CellMatrix(ArrayFlags, Dimensions, String, Vec<DataElement>),
+    StructMatrix(ArrayFlags, Dimensions, String, FieldNames, Vec<DataElement>),
+    ObjectMatrix(
+        ArrayFlags,
+        Dimensions,
+        String,
+        ClassName,
+        FieldNames,
+        Vec<DataElement>,
+    ),
+    FunctionMatrix(Dimensions, String, Box<DataElement>),
+    OpaqueMatrix(String, String, ClassName, Box<DataElement>),
     Unsupported,
 }
 #[derive(Clone, Debug)]
@@ -294,6 +307,8 @@ pub struct DataElementTag {
 pub struct ParseResult {
     pub header: Header,
     pub data_elements: Vec<DataElement>,
+    /// The element at `header.subsys_offset`, which holds the MCOS object data.
+    pub subsystem: Option<DataElement>,
 }
 
 pub fn parse_header(i: &[u8]) -> IResult<&[u8], Header> {
@@ -516,6 +531,181 @@ fn parse_sparse_matrix_subelements(
         ),
     ))
 }
+//字符矩阵的数据可能以miUTF8、miUTF16、miUTF32或旧版的miUINT16存储
+fn parse_char_subelement(
+    i: &[u8],
+    endianness: nom::number::Endianness,
+) -> IResult<&[u8], CharData> {
+    let (input, data_element_tag) = parse_data_element_tag(i, endianness)?;
+    let byte_size = data_element_tag.data_byte_size as usize;
+    let (input, chars) = match data_element_tag.data_type {
+        DataType::Utf16 | DataType::UInt16 | DataType::Int16 => {
+            count(u16(endianness), byte_size / 2)(input)?
+        }
+        DataType::Utf8 => map_res(take(byte_size), |b| {
+            std::str::from_utf8(b).map(|s| s.encode_utf16().collect::<CharData>())
+        })(input)?,
+        DataType::Utf32 => map_res(count(u32(endianness), byte_size / 4), |code_points| {
+            code_points
+                .into_iter()
+                .map(|c| char::from_u32(c).ok_or(nom::error::ErrorKind::Char))
+                .collect::<Result<String, _>>()
+                .map(|s| s.encode_utf16().collect::<CharData>())
+        })(input)?,
+        DataType::UInt8 | DataType::Int8 => map(take(byte_size), |b: &[u8]| {
+            b.iter().map(|&c| c as u16).collect::<CharData>()
+        })(input)?,
+        _ => {
+            return Err(nom::Err::Failure(error_position!(
+                i,
+                nom::error::ErrorKind::Tag
+            )));
+        }
+    };
+    let (input, _) = take(data_element_tag.padding_byte_size)(input)?;
+    Ok((input, chars))
+}
+fn parse_char_matrix_subelements(
+    i: &[u8],
+    endianness: nom::number::Endianness,
+    flags: ArrayFlags,
+) -> IResult<&[u8], DataElement> {
+    let (input, dimensions) = parse_dimensions_array_subelement(i, endianness)?;
+    let (input, name) = parse_array_name_subelement(input, endianness)?;
+    let (input, data) = parse_char_subelement(input, endianness)?;
+    Ok((
+        input,
+        DataElement::CharMatrix(flags, dimensions, name, data),
+    ))
+}
+//元素个数,维数为负或乘积溢出时返回错误
+fn num_elements<'a>(i: &'a [u8], dimensions: &Dimensions) -> IResult<&'a [u8], usize> {
+    let num_elements = dimensions.iter().try_fold(1usize, |product, &d| {
+        usize::try_from(d).ok().and_then(|d| product.checked_mul(d))
+    });
+    match num_elements {
+        Some(num_elements) => Ok((i, num_elements)),
+        None => Err(nom::Err::Failure(error_position!(
+            i,
+            nom::error::ErrorKind::Verify
+        ))),
+    }
+}
+//cell的每个元素都是一个完整的miMATRIX,按列存储
+fn parse_cell_matrix_subelements(
+    i: &[u8],
+    endianness: nom::number::Endianness,
+    flags: ArrayFlags,
+) -> IResult<&[u8], DataElement> {
+    let (input, dimensions) = parse_dimensions_array_subelement(i, endianness)?;
+    let (input, name) = parse_array_name_subelement(input, endianness)?;
+    let (input, num_elements) = num_elements(input, &dimensions)?;
+    let (input, cells) = count(
+        |input| parse_next_data_element(input, endianness),
+        num_elements,
+    )(input)?;
+    Ok((
+        input,
+        DataElement::CellMatrix(flags, dimensions, name, cells),
+    ))
+}
+//字段名长度子元素之后是按该长度补齐、以0结尾的字段名
+fn parse_field_names_subelements(
+    i: &[u8],
+    endianness: nom::number::Endianness,
+) -> IResult<&[u8], FieldNames> {
+    let (input, data_element_tag) = parse_data_element_tag(i, endianness)?;
+    let (input, field_name_length) = i32(endianness)(input)?;
+    let (input, _) = take(data_element_tag.padding_byte_size)(input)?;
+    let (input, data_element_tag) = parse_data_element_tag(input, endianness)?;
+    let (input, names) = take(data_element_tag.data_byte_size)(input)?;
+    let (input, _) = take(data_element_tag.padding_byte_size)(input)?;
+    if field_name_length <= 0 {
+        return Ok((input, vec![]));
+    }
+    let field_names = names
+        .chunks(field_name_length as usize)
+        .map(|name| {
+            let end = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+            String::from_utf8_lossy(&name[..end]).into_owned()
+        })
+        .collect();
+    Ok((input, field_names))
+}
+//结构体数组按列存储,每个元素依次存放所有字段的miMATRIX
+fn parse_struct_matrix_subelements(
+    i: &[u8],
+    endianness: nom::number::Endianness,
+    flags: ArrayFlags,
+) -> IResult<&[u8], DataElement> {
+    let (input, dimensions) = parse_dimensions_array_subelement(i, endianness)?;
+    let (input, name) = parse_array_name_subelement(input, endianness)?;
+    let (input, field_names) = parse_field_names_subelements(input, endianness)?;
+    let (input, num_elements) = num_elements(input, &dimensions)?;
+    let num_fields = num_elements
+        .checked_mul(field_names.len())
+        .ok_or_else(|| nom::Err::Failure(error_position!(input, nom::error::ErrorKind::Verify)))?;
+    let (input, fields) = count(
+        |input| parse_next_data_element(input, endianness),
+        num_fields,
+    )(input)?;
+    Ok((
+        input,
+        DataElement::StructMatrix(flags, dimensions, name, field_names, fields),
+    ))
+}
+//对象与结构体相同,只是在字段名之前多了类名
+fn parse_object_matrix_subelements(
+    i: &[u8],
+    endianness: nom::number::Endianness,
+    flags: ArrayFlags,
+) -> IResult<&[u8], DataElement> {
+    let (input, dimensions) = parse_dimensions_array_subelement(i, endianness)?;
+    let (input, name) = parse_array_name_subelement(input, endianness)?;
+    let (input, class_name) = parse_array_name_subelement(input, endianness)?;
+    let (input, field_names) = parse_field_names_subelements(input, endianness)?;
+    let (input, num_elements) = num_elements(input, &dimensions)?;
+    let num_fields = num_elements
+        .checked_mul(field_names.len())
+        .ok_or_else(|| nom::Err::Failure(error_position!(input, nom::error::ErrorKind::Verify)))?;
+    let (input, fields) = count(
+        |input| parse_next_data_element(input, endianness),
+        num_fields,
+    )(input)?;
+    Ok((
+        input,
+        DataElement::ObjectMatrix(flags, dimensions, name, class_name, field_names, fields),
+    ))
+}
+//函数句柄的内容保存在一个嵌套的miMATRIX中
+fn parse_function_matrix_subelements(
+    i: &[u8],
+    endianness: nom::number::Endianness,
+) -> IResult<&[u8], DataElement> {
+    let (input, dimensions) = parse_dimensions_array_subelement(i, endianness)?;
+    let (input, name) = parse_array_name_subelement(input, endianness)?;
+    let (input, data) = parse_next_data_element(input, endianness)?;
+    Ok((
+        input,
+        DataElement::FunctionMatrix(dimensions, name, Box::new(data)),
+    ))
+}
+//字节数为0的miMATRIX表示空矩阵[]
+fn empty_matrix_data_element() -> DataElement {
+    DataElement::NumericMatrix(
+        ArrayFlags {
+            complex: false,
+            global: false,
+            logical: false,
+            class: MatlabType::Double,
+            nzmax: 0,
+        },
+        vec![0, 0],
+        String::new(),
+        NumericData::Double(vec![]),
+        None,
+    )
+}
 fn parse_unsupported_data_element(
     _i: &[u8],
     _endianness: nom::number::Endianness,
@@ -529,45 +719,30 @@ fn parse_matrix_data_element(
     let (input, flags) = parse_array_flags_subelement(i, endianness)?;
     println!("arrayflags={:?}  class={:?}", flags, flags.class);
     let (input, data_element) = match flags.class {
-        MatlabType::Cell => parse_unsupported_data_element(input, endianness)?,
-        MatlabType::Struct => parse_unsupported_data_element(input, endianness)?,
-        MatlabType::Object => parse_unsupported_data_element(input, endianness)?,
-        MatlabType::Char => parse_unsupported_data_element(input, endianness)?,
+        MatlabType::Cell => parse_cell_matrix_subelements(input, endianness, flags)?,
+        MatlabType::Struct => parse_struct_matrix_subelements(input, endianness, flags)?,
+        MatlabType::Object => parse_object_matrix_subelements(input, endianness, flags)?,
+        MatlabType::Char => parse_char_matrix_subelements(input, endianness, flags)?,
         MatlabType::Sparse => parse_sparse_matrix_subelements(input, endianness, flags)?,
+        MatlabType::Function => parse_function_matrix_subelements(input, endianness)?,
         MatlabType::Opaque => parse_opaque_matrix_subelements(input, endianness)?,
         _ => parse_numeric_matrix_subelements(input, endianness, flags)?,
     };
     Ok((input, data_element))
 }
+//opaque没有维数子元素:依次为变量名、对象类型(如MCOS)、类名和一个嵌套的miMATRIX
 fn parse_opaque_matrix_subelements(
     i: &[u8],
     endianness: nom::number::Endianness,
 ) -> IResult<&[u8], DataElement> {
-    println!("parse_opaque");
-    let (input, data_element_tag) = parse_data_element_tag(i, endianness)?;
-    let (input, name) = take(data_element_tag.data_byte_size)(input)?;
-    println!("name={:?}", String::from_utf8_lossy(name));
-    let (input, _) = take(data_element_tag.padding_byte_size)(input)?;
-
-    let (input, data_element_tag) = parse_data_element_tag(input, endianness)?;
-    let (input, object_type) = take(data_element_tag.data_byte_size)(input)?;
-    let (input, _) = take(data_element_tag.padding_byte_size)(input)?;
-    println!(
-        "objectType={:?} {:?}",
-        String::from_utf8_lossy(object_type),
-        data_element_tag.data_byte_size
-    );
-    let (input, data_element_tag) = parse_data_element_tag(input, endianness)?;
-    let (input, class_name) = take(data_element_tag.data_byte_size)(input)?;
-    let (input, _) = take(data_element_tag.padding_byte_size)(input)?;
-    println!(
-        "className={:?} {:?}",
-        String::from_utf8_lossy(class_name),
-        data_element_tag.data_byte_size
-    );
-    let (input, dimensions) = parse_dimensions_array_subelement(input, endianness)?;
-    println!("dimensions==>{:?}", dimensions);
-    Ok((input, DataElement::Unsupported))
+    let (input, name) = parse_array_name_subelement(i, endianness)?;
+    let (input, object_type) = parse_array_name_subelement(input, endianness)?;
+    let (input, class_name) = parse_array_name_subelement(input, endianness)?;
+    let (input, data) = parse_next_data_element(input, endianness)?;
+    Ok((
+        input,
+        DataElement::OpaqueMatrix(name, object_type, class_name, Box::new(data)),
+    ))
 }
 
 pub fn parse_compressed_data_element(
@@ -750,7 +925,7 @@ fn parse_numeric_subelement(
             )));
         }
     };
-    take(data_element_tag.padding_byte_size)(input)?;
+    let (input, _) = take(data_element_tag.padding_byte_size)(input)?;
     Ok((input, numeric_data))
 }
 
@@ -784,7 +959,16 @@ fn parse_next_data_element(
     let (input, data_element_tag) = parse_data_element_tag(i, endianness)?;
     println!("data_element_tag=={:?}", data_element_tag);
     let (input, data_element) = match data_element_tag.data_type {
-        DataType::Matrix => parse_matrix_data_element(input, endianness)?,
+        DataType::Matrix => {
+            //按tag中的字节数截取,避免子元素解析不完整时影响后续数据
+            let (input, matrix) = take(data_element_tag.data_byte_size)(input)?;
+            if matrix.is_empty() {
+                (input, empty_matrix_data_element())
+            } else {
+                let (_, data_element) = parse_matrix_data_element(matrix, endianness)?;
+                (input, data_element)
+            }
+        }
         DataType::Compressed => {
             parse_compressed_data_element(input, endianness, data_element_tag.data_byte_size)?
         }
@@ -799,11 +983,18 @@ pub fn parse_all(i: &[u8]) -> IResult<&[u8], ParseResult> {
     let (mut input, header) = parse_header(i)?;
     println!("{:?}", header);
     let mut data_elements = vec![];
+    let mut subsystem = None;
     loop {
+        let offset = (i.len() - input.len()) as u64;
         match parse_next_data_element(input, header.byte_order) {
             Ok((new_input, data_element)) => {
                 input = new_input;
-                data_elements.push(data_element);
+                //子系统数据不是变量,单独保存
+                if offset == header.subsys_offset {
+                    subsystem = Some(data_element);
+                } else {
+                    data_elements.push(data_element);
+                }
             }
             _ => {
                 break;
@@ -816,20 +1007,42 @@ pub fn parse_all(i: &[u8]) -> IResult<&[u8], ParseResult> {
         ParseResult {
             header: header,
             data_elements: data_elements,
+            subsystem,
         },
     ))
 }
 
+//子系统数据的内容是去掉文本头的MAT文件:8字节的版本和字节序之后是数据元素
+pub fn parse_subsystem(
+    i: &[u8],
+    endianness: nom::number::Endianness,
+) -> IResult<&[u8], Vec<DataElement>> {
+    let (mut input, _) = take(8usize)(i)?;
+    let mut data_elements = vec![];
+    while !input.is_empty() {
+        let (new_input, data_element) = parse_next_data_element(input, endianness)?;
+        input = new_input;
+        data_elements.push(data_element);
+    }
+    Ok((input, data_elements))
+}
+
+#[cfg(test)]
 mod tests {
     use crate::parse::le_f64;
     use crate::parse::DataElement;
+    use crate::test_fixtures::{matrix_subelements, subelement};
     use nom::multi::count;
     use nom::IResult;
 
     #[test]
     fn name() {
-        let data = include_bytes!("d:/myfile.mat");
-        let r = super::parse_all(data).unwrap();
+        //basic.mat由本库写出,包含数值、字符、逻辑、cell、结构体和稀疏矩阵各一个
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/basic.mat");
+        let data = std::fs::read(path).unwrap();
+        let (_, result) = super::parse_all(&data).unwrap();
+        assert_eq!(result.data_elements.len(), 6);
+        assert!(result.subsystem.is_none());
     }
     #[test]
     fn test_product() {
@@ -853,4 +1066,203 @@ mod tests {
         println!("{:?}", result);
         Ok((&[], DataElement::Unsupported))
     }
+
+    #[test]
+    fn parse_char_matrix() {
+        let endianness = nom::number::Endianness::Little;
+        //2x3的字符矩阵["abc";"def"]按列存储为miUTF16
+        let mut bytes = matrix_subelements(4, &[2, 3], "label");
+        let chars: Vec<u8> = "adbecf"
+            .encode_utf16()
+            .flat_map(|c| c.to_le_bytes())
+            .collect();
+        bytes.extend(subelement(17, &chars));
+        let (_, data_element) = super::parse_matrix_data_element(&bytes, endianness).unwrap();
+        match data_element {
+            DataElement::CharMatrix(flags, dims, name, data) => {
+                assert_eq!(flags.class, super::MatlabType::Char);
+                assert_eq!(dims, vec![2, 3]);
+                assert_eq!(name, "label");
+                assert_eq!(String::from_utf16_lossy(&data), "adbecf");
+            }
+            _ => panic!("expected a char matrix"),
+        }
+        //1x2的字符串以Small Data Element Format的miUTF8存储
+        let mut bytes = matrix_subelements(4, &[1, 2], "unit");
+        bytes.extend_from_slice(&(2u32 << 16 | 16).to_le_bytes());
+        bytes.extend_from_slice(&[b'm', b's', 0, 0]);
+        let (_, data_element) = super::parse_matrix_data_element(&bytes, endianness).unwrap();
+        match data_element {
+            DataElement::CharMatrix(_, _, _, data) => {
+                assert_eq!(String::from_utf16_lossy(&data), "ms");
+            }
+            _ => panic!("expected a char matrix"),
+        }
+    }
+
+    #[test]
+    fn parse_cell_matrix() {
+        let endianness = nom::number::Endianness::Little;
+        //1x3的cell: {5, 'ms', []}
+        let mut number = matrix_subelements(6, &[1, 1], "");
+        number.extend(subelement(2, &[5]));
+        let mut text = matrix_subelements(4, &[1, 2], "");
+        text.extend(subelement(4, &[b'm', 0, b's', 0]));
+        let mut bytes = matrix_subelements(1, &[1, 3], "trials");
+        for element in [number, text, vec![]] {
+            bytes.extend(subelement(14, &element));
+        }
+        let (_, data_element) = super::parse_matrix_data_element(&bytes, endianness).unwrap();
+        let cells = match data_element {
+            DataElement::CellMatrix(_, dims, name, cells) => {
+                assert_eq!(dims, vec![1, 3]);
+                assert_eq!(name, "trials");
+                cells
+            }
+            _ => panic!("expected a cell matrix"),
+        };
+        assert_eq!(cells.len(), 3);
+        match &cells[0] {
+            DataElement::NumericMatrix(flags, _, _, real, None) => {
+                assert_eq!(flags.class, super::MatlabType::Double);
+                assert_eq!(real, &super::NumericData::UInt8(vec![5]));
+            }
+            _ => panic!("expected a numeric matrix"),
+        }
+        match &cells[1] {
+            DataElement::CharMatrix(_, _, _, data) => {
+                assert_eq!(String::from_utf16_lossy(data), "ms");
+            }
+            _ => panic!("expected a char matrix"),
+        }
+        match &cells[2] {
+            DataElement::NumericMatrix(_, dims, _, real, None) => {
+                assert_eq!(dims, &vec![0, 0]);
+                assert_eq!(real, &super::NumericData::Double(vec![]));
+            }
+            _ => panic!("expected an empty matrix"),
+        }
+    }
+
+    #[test]
+    fn parse_cell_matrix_with_bad_dimensions() {
+        let endianness = nom::number::Endianness::Little;
+        //负的维数和超出i32的元素个数都应返回错误而不是panic
+        for dims in [[-1, 2], [65536, 65536]] {
+            let bytes = matrix_subelements(1, &dims, "c");
+            assert!(super::parse_matrix_data_element(&bytes, endianness).is_err());
+        }
+    }
+
+    #[test]
+    fn parse_struct_matrix() {
+        let endianness = nom::number::Endianness::Little;
+        //2x1的结构体数组, 字段为gain和label
+        let mut bytes = matrix_subelements(2, &[2, 1], "cfg");
+        bytes.extend_from_slice(&(4u32 << 16 | 5).to_le_bytes());
+        bytes.extend_from_slice(&8i32.to_le_bytes());
+        bytes.extend(subelement(1, b"gain\0\0\0\0label\0\0\0"));
+        for (gain, label) in [(2u8, "a"), (3u8, "b")] {
+            let mut number = matrix_subelements(6, &[1, 1], "");
+            number.extend(subelement(2, &[gain]));
+            let mut text = matrix_subelements(4, &[1, 1], "");
+            text.extend(subelement(16, label.as_bytes()));
+            for element in [number, text] {
+                bytes.extend(subelement(14, &element));
+            }
+        }
+        let (_, data_element) = super::parse_matrix_data_element(&bytes, endianness).unwrap();
+        match data_element {
+            DataElement::StructMatrix(_, dims, name, field_names, fields) => {
+                assert_eq!(dims, vec![2, 1]);
+                assert_eq!(name, "cfg");
+                assert_eq!(field_names, vec!["gain".to_string(), "label".to_string()]);
+                assert_eq!(fields.len(), 4);
+                match &fields[2] {
+                    DataElement::NumericMatrix(_, _, _, real, _) => {
+                        assert_eq!(real, &super::NumericData::UInt8(vec![3]));
+                    }
+                    _ => panic!("expected a numeric matrix"),
+                }
+            }
+            _ => panic!("expected a struct matrix"),
+        }
+    }
+
+    #[test]
+    fn parse_struct_matrix_with_bad_dimensions() {
+        let endianness = nom::number::Endianness::Little;
+        for dims in [[2, -1], [65536, 65536]] {
+            let mut bytes = matrix_subelements(2, &dims, "cfg");
+            bytes.extend_from_slice(&(4u32 << 16 | 5).to_le_bytes());
+            bytes.extend_from_slice(&8i32.to_le_bytes());
+            bytes.extend(subelement(1, b"gain\0\0\0\0"));
+            assert!(super::parse_matrix_data_element(&bytes, endianness).is_err());
+        }
+    }
+
+    #[test]
+    fn parse_complex_sparse_matrix() {
+        let endianness = nom::number::Endianness::Little;
+        //3x2的复数稀疏矩阵, 实部压缩为miUINT8并带有补齐字节
+        let mut bytes = subelement(6, &[5, 0x08, 0, 0, 2, 0, 0, 0]);
+        let dims: Vec<u8> = [3i32, 2].iter().flat_map(|d| d.to_le_bytes()).collect();
+        bytes.extend(subelement(5, &dims));
+        bytes.extend(subelement(1, b"k"));
+        let ir: Vec<u8> = [2i32, 0].iter().flat_map(|d| d.to_le_bytes()).collect();
+        bytes.extend(subelement(5, &ir));
+        let jc: Vec<u8> = [0i32, 1, 2].iter().flat_map(|d| d.to_le_bytes()).collect();
+        bytes.extend(subelement(5, &jc));
+        bytes.extend(subelement(2, &[7, 9]));
+        let imag: Vec<u8> = [0.5f64, -1.0]
+            .iter()
+            .flat_map(|d| d.to_le_bytes())
+            .collect();
+        bytes.extend(subelement(9, &imag));
+        let (_, data_element) = super::parse_matrix_data_element(&bytes, endianness).unwrap();
+        match data_element {
+            DataElement::SparseMatrix(flags, dims, _, ir, jc, real, imag) => {
+                assert!(flags.complex);
+                assert_eq!(flags.nzmax, 2);
+                assert_eq!(dims, vec![3, 2]);
+                assert_eq!(ir, vec![2, 0]);
+                assert_eq!(jc, vec![0, 1, 2]);
+                assert_eq!(real, super::NumericData::UInt8(vec![7, 9]));
+                assert_eq!(imag, Some(super::NumericData::Double(vec![0.5, -1.0])));
+            }
+            _ => panic!("expected a sparse matrix"),
+        }
+    }
+
+    #[test]
+    fn parse_opaque_matrix() {
+        let endianness = nom::number::Endianness::Little;
+        let mut bytes = subelement(6, &[17, 0, 0, 0, 0, 0, 0, 0]);
+        bytes.extend(subelement(1, b"s"));
+        bytes.extend(subelement(1, b"MCOS"));
+        bytes.extend(subelement(1, b"string"));
+        let mut reference = matrix_subelements(13, &[6, 1], "");
+        let ids: Vec<u8> = [0xdd000000u32, 2, 1, 1, 1, 1]
+            .iter()
+            .flat_map(|d| d.to_le_bytes())
+            .collect();
+        reference.extend(subelement(6, &ids));
+        bytes.extend(subelement(14, &reference));
+        let (_, data_element) = super::parse_matrix_data_element(&bytes, endianness).unwrap();
+        match data_element {
+            DataElement::OpaqueMatrix(name, object_type, class_name, data) => {
+                assert_eq!(name, "s");
+                assert_eq!(object_type, "MCOS");
+                assert_eq!(class_name, "string");
+                match *data {
+                    DataElement::NumericMatrix(_, dims, _, real, _) => {
+                        assert_eq!(dims, vec![6, 1]);
+                        assert_eq!(real.len(), 6);
+                    }
+                    _ => panic!("expected a numeric matrix"),
+                }
+            }
+            _ => panic!("expected an opaque matrix"),
+        }
+    }
 }
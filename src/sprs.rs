@@ -0,0 +1,125 @@
+//! Conversions between [`SparseArray`] and `sprs` compressed matrices.
+//!
+//! MATLAB keeps sparse matrices in CSC form, so CSC matrices convert without
+//! reordering; CSR matrices are converted to CSC first.
+use num_complex::Complex64;
+use spr::CsMat;
+
+use crate::mat_error::MatError;
+use crate::SparseArray;
+
+impl TryFrom<&SparseArray> for CsMat<f64> {
+    type Error = MatError;
+    /// Fails if the sparse matrix is complex. Logical matrices give ones.
+    fn try_from(sparse: &SparseArray) -> Result<Self, Self::Error> {
+        if sparse.imag.is_some() {
+            return Err(MatError::ParamsError(format!(
+                "变量{}是复数稀疏矩阵,请转换为CsMat<Complex64>",
+                sparse.name
+            )));
+        }
+        new_csc(sparse, sparse.real.clone())
+    }
+}
+
+impl TryFrom<&SparseArray> for CsMat<Complex64> {
+    type Error = MatError;
+    /// Real sparse matrices are accepted and get a zero imaginary part.
+    fn try_from(sparse: &SparseArray) -> Result<Self, Self::Error> {
+        let data = match &sparse.imag {
+            Some(imag) => sparse
+                .real
+                .iter()
+                .zip(imag)
+                .map(|(&re, &im)| Complex64::new(re, im))
+                .collect(),
+            None => sparse
+                .real
+                .iter()
+                .map(|&re| Complex64::new(re, 0.0))
+                .collect(),
+        };
+        new_csc(sparse, data)
+    }
+}
+
+impl SparseArray {
+    /// A sparse matrix from a `sprs` matrix; explicitly stored zeros are kept.
+    pub fn from_csmat(name: &str, matrix: &CsMat<f64>) -> Result<SparseArray, MatError> {
+        let (ir, jc, real) = csc_parts(matrix);
+        SparseArray::from_csc(name, matrix.rows(), matrix.cols(), ir, jc, real, None)
+    }
+    /// A complex sparse matrix from a `sprs` matrix of `Complex64`.
+    pub fn from_complex_csmat(
+        name: &str,
+        matrix: &CsMat<Complex64>,
+    ) -> Result<SparseArray, MatError> {
+        let (ir, jc, data) = csc_parts(matrix);
+        let real = data.iter().map(|c| c.re).collect();
+        let imag = data.iter().map(|c| c.im).collect();
+        SparseArray::from_csc(name, matrix.rows(), matrix.cols(), ir, jc, real, Some(imag))
+    }
+}
+
+fn new_csc<N>(sparse: &SparseArray, data: Vec<N>) -> Result<CsMat<N>, MatError> {
+    let shape = match sparse.size.as_slice() {
+        &[rows, cols] => (rows, cols),
+        _ => {
+            return Err(MatError::ParamsError(format!(
+                "稀疏矩阵{}不是二维矩阵",
+                sparse.name
+            )))
+        }
+    };
+    CsMat::try_new_csc(shape, sparse.jc.clone(), sparse.ir.clone(), data)
+        .map_err(|e| MatError::ParamsError(format!("稀疏矩阵{}的结构无效: {}", sparse.name, e.3)))
+}
+
+fn csc_parts<N: Clone + Default>(matrix: &CsMat<N>) -> (Vec<usize>, Vec<usize>, Vec<N>) {
+    let matrix = matrix.to_csc();
+    let offset = matrix.indptr().raw_storage()[0];
+    let nnz = matrix.nnz();
+    (
+        matrix.indices()[offset..offset + nnz].to_vec(),
+        matrix.indptr().to_proper().to_vec(),
+        matrix.data()[offset..offset + nnz].to_vec(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use num_complex::Complex64;
+    use spr::{CsMat, TriMat};
+
+    use crate::SparseArray;
+
+    #[test]
+    fn csmat_round_trip() -> Result<(), crate::mat_error::MatError> {
+        let sparse = SparseArray::from_triplets("k", 3, 2, &[(2, 0, 1.5), (0, 1, -2.0)])?;
+        let matrix = CsMat::<f64>::try_from(&sparse)?;
+        assert_eq!(matrix.get(2, 0), Some(&1.5));
+        assert_eq!(matrix.get(0, 1), Some(&-2.0));
+        assert!(CsMat::<f64>::try_from(&sparse.clone().into_logical()?)?.get(2, 0) == Some(&1.0));
+
+        let mut triplets = TriMat::new((3, 2));
+        triplets.add_triplet(2, 0, 1.5);
+        triplets.add_triplet(0, 1, -2.0);
+        let back = SparseArray::from_csmat("k", &triplets.to_csr())?;
+        assert_eq!(back.ir(), sparse.ir());
+        assert_eq!(back.jc(), sparse.jc());
+        assert_eq!(back.real(), sparse.real());
+        Ok(())
+    }
+
+    #[test]
+    fn complex_csmat_round_trip() -> Result<(), crate::mat_error::MatError> {
+        let sparse = SparseArray::from_complex_triplets("z", 2, 2, &[(1, 1, 1.0, -1.0)])?;
+        assert!(CsMat::<f64>::try_from(&sparse).is_err());
+        let matrix = CsMat::<Complex64>::try_from(&sparse)?;
+        assert_eq!(matrix.get(1, 1), Some(&Complex64::new(1.0, -1.0)));
+        let back = SparseArray::from_complex_csmat("z", &matrix)?;
+        assert_eq!(back.imag(), Some(&[-1.0][..]));
+        assert_eq!(back.size(), &vec![2, 2]);
+        Ok(())
+    }
+}
@@ -0,0 +1,65 @@
+//! Byte-level builders for little-endian MAT-file fixtures used by the unit tests.
+
+/// A data element in the long format, padded to 8 bytes.
+pub(crate) fn subelement(data_type: u32, data: &[u8]) -> Vec<u8> {
+    let mut bytes = data_type.to_le_bytes().to_vec();
+    bytes.extend((data.len() as u32).to_le_bytes());
+    bytes.extend(data);
+    bytes.resize(bytes.len().next_multiple_of(8), 0);
+    bytes
+}
+
+/// A data element in the small format when 1 to 4 bytes long, as MATLAB writes it.
+pub(crate) fn small_subelement(data_type: u32, data: &[u8]) -> Vec<u8> {
+    if data.is_empty() || data.len() > 4 {
+        return subelement(data_type, data);
+    }
+    let mut bytes = ((data.len() as u32) << 16 | data_type)
+        .to_le_bytes()
+        .to_vec();
+    bytes.extend(data);
+    bytes.resize(8, 0);
+    bytes
+}
+
+/// The array flags, dimensions and name subelements that start every miMATRIX.
+pub(crate) fn matrix_subelements(class: u8, dims: &[i32], name: &str) -> Vec<u8> {
+    let mut bytes = subelement(6, &[class, 0, 0, 0, 0, 0, 0, 0]);
+    let dims: Vec<u8> = dims.iter().flat_map(|d| d.to_le_bytes()).collect();
+    bytes.extend(subelement(5, &dims));
+    bytes.extend(subelement(1, name.as_bytes()));
+    bytes
+}
+
+/// A whole miMATRIX element; opaque arrays pass `None` as they have no dimensions.
+pub(crate) fn matrix(class: u8, dims: Option<&[i32]>, name: &str, rest: &[u8]) -> Vec<u8> {
+    let mut body = subelement(6, &[class, 0, 0, 0, 0, 0, 0, 0]);
+    if let Some(dims) = dims {
+        let dims: Vec<u8> = dims.iter().flat_map(|d| d.to_le_bytes()).collect();
+        body.extend(subelement(5, &dims));
+    }
+    body.extend(subelement(1, name.as_bytes()));
+    body.extend(rest);
+    subelement(14, &body)
+}
+
+/// A MAT-file: the 128-byte header, the variables, then the subsystem if any.
+///
+/// The subsystem is the content of the uint8 variable that the header's
+/// subsystem offset points at.
+pub(crate) fn mat_file(variables: &[Vec<u8>], subsystem: Option<&[u8]>) -> Vec<u8> {
+    let variables = variables.concat();
+    let subsys_offset = match subsystem {
+        Some(_) => 128 + variables.len() as u64,
+        None => 0,
+    };
+    let mut bytes = vec![b' '; 116];
+    bytes.extend(subsys_offset.to_le_bytes());
+    bytes.extend([0x00, 0x01, b'I', b'M']);
+    bytes.extend(variables);
+    if let Some(subsystem) = subsystem {
+        let dims = [subsystem.len() as i32, 1];
+        bytes.extend(matrix(9, Some(&dims), "", &subelement(2, subsystem)));
+    }
+    bytes
+}
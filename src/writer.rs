@@ -1,9 +1,9 @@
 use std::io::{Read, Write};
 
 use crate::parse::{self, ArrayFlags, DataElement, DataElementTag, DataType};
-use crate::Array;
 use crate::NumericData;
 use crate::{mat_error::MatError, MatFile};
+use crate::{Array, CellArray, CharArray, LogicalArray, MatValue, SparseArray, StructArray};
 use bytes::{BufMut, BytesMut};
 use libflate::deflate::EncodeOptions;
 use libflate::zlib::{Decoder, Encoder, Header};
@@ -26,8 +26,8 @@ pub fn write_header(mat: &MatFile) -> Result<BytesMut, MatError> {
 }
 pub fn write_body(mat: &MatFile) -> Result<BytesMut, MatError> {
     let mut body_bytes = BytesMut::new();
-    for arrays in mat.arrays.iter() {
-        let data_element = write_next_data_element(arrays, mat.header.byte_order)?;
+    for value in mat.values.iter() {
+        let data_element = write_next_data_element(value, mat.header.byte_order)?;
         body_bytes.put_slice(&data_element);
     }
     Ok(body_bytes)
@@ -35,12 +35,12 @@ pub fn write_body(mat: &MatFile) -> Result<BytesMut, MatError> {
 
 // pub fn write_array_deflated(name:&str,array: &Array,)
 fn write_next_data_element(
-    array: &Array,
+    value: &MatValue,
     endianness: nom::number::Endianness,
 ) -> Result<BytesMut, MatError> {
     let mut bytes = BytesMut::new();
     //设置Compressed方式
-    let data_element_bytes = write_matrix_data_element(array, endianness)?;
+    let data_element_bytes = write_matrix_data_element(value.name(), value, endianness)?;
     println!("{:?}", data_element_bytes.to_vec());
     //加密矩阵的数组
     let compress_bytes = write_compressed_data_element(&data_element_bytes)?;
@@ -64,14 +64,14 @@ fn write_compressed_data_element(source_bytes: &[u8]) -> Result<BytesMut, MatErr
 }
 
 //生成data_element_tag
-////如果data_byte_size小于4采用Small Data Element Format
+//如果data_byte_size在1到4之间采用Small Data Element Format
 fn write_data_element_tag(
     data_type: DataType,
     data_byte_size: u32,
     endianness: nom::number::Endianness,
 ) -> Result<(BytesMut, u32), MatError> {
     let mut bytes = BytesMut::new();
-    let packed = if data_byte_size < 4 { true } else { false };
+    let packed = data_byte_size > 0 && data_byte_size <= 4;
     if !packed {
         // Long Data Element Format
         let data_type = data_type as u32;
@@ -85,18 +85,14 @@ fn write_data_element_tag(
         }
         Ok((bytes, if padding == 0 { 0 } else { 8 - padding }))
     } else {
-        // Small Data Element Format
-        let data_type = data_type as u8;
-        let data_byte_size = data_byte_size as u8;
+        // Small Data Element Format: 高16位为字节数,低16位为数据类型
+        let tag = (data_byte_size << 16) | data_type as u32;
         if endianness == nom::number::Endianness::Big {
-            bytes.put_slice(&data_type.to_be_bytes());
-            bytes.put_slice(&data_byte_size.to_be_bytes());
+            bytes.put_slice(&tag.to_be_bytes());
         } else {
-            bytes.put_slice(&data_type.to_le_bytes());
-            bytes.put_slice(&data_byte_size.to_le_bytes());
+            bytes.put_slice(&tag.to_le_bytes());
         }
-        let padding = data_byte_size % 4;
-        Ok((bytes, padding as u32))
+        Ok((bytes, 4 - data_byte_size))
     }
 }
 fn get_limited_name_size(name: &str) -> Result<usize, MatError> {
@@ -108,14 +104,51 @@ fn get_limited_name_size(name: &str) -> Result<usize, MatError> {
     Ok(len)
 }
 
+//cell和结构体中嵌套的miMATRIX名称为空,因此名称单独传入
 fn write_matrix_data_element(
-    array: &Array,
+    name: &str,
+    value: &MatValue,
     endianness: nom::number::Endianness,
 ) -> Result<BytesMut, MatError> {
     let mut bytes = BytesMut::new();
-    let array_flags_bytes = write_array_flags_subelement(&array.array_flags, endianness)?;
+    let (array_flags, subelements_bytes) = match value {
+        MatValue::Numeric(array) => (
+            array.array_flags,
+            write_numeric_matrix_subelements(name, array, endianness)?,
+        ),
+        MatValue::Logical(array) => (
+            array.array_flags,
+            write_logical_matrix_subelements(name, array, endianness)?,
+        ),
+        MatValue::Char(array) => (
+            array.array_flags,
+            write_char_matrix_subelements(name, array, endianness)?,
+        ),
+        MatValue::Cell(array) => (
+            array.array_flags,
+            write_cell_matrix_subelements(name, array, endianness)?,
+        ),
+        MatValue::Struct(array) => (
+            array.array_flags,
+            write_struct_matrix_subelements(name, array, endianness)?,
+        ),
+        //读入的稀疏矩阵只保留了nnz个元素,nzmax与之保持一致
+        MatValue::Sparse(array) => (
+            ArrayFlags {
+                nzmax: array.nnz(),
+                ..array.array_flags
+            },
+            write_sparse_matrix_subelements(name, array, endianness)?,
+        ),
+        _ => {
+            return Err(MatError::ParamsError(format!(
+                "暂不支持写入变量{}的数据类型",
+                value.name()
+            )))
+        }
+    };
+    let array_flags_bytes = write_array_flags_subelement(&array_flags, endianness)?;
     println!("flag={:?}", array_flags_bytes.to_vec());
-    let subelements_bytes = write_numeric_matrix_subelements(array, endianness)?;
     println!("subelements_bytes={:?}", subelements_bytes.to_vec());
     let data_bytes_size = array_flags_bytes.len() + subelements_bytes.len();
     let (tag_bytes, padding) =
@@ -162,21 +195,66 @@ fn write_array_flags_subelement(
 
 //写入数字类型矩阵
 fn write_numeric_matrix_subelements(
+    name: &str,
     array: &Array,
     endianness: nom::number::Endianness,
 ) -> Result<BytesMut, MatError> {
     let mut bytes = BytesMut::new();
     //写入矩阵维数
-    let dimensions_bytes = write_dimensions_array_subelement(array, endianness)?;
+    let dimensions_bytes = write_dimensions_array_subelement(&array.size, endianness)?;
     bytes.put_slice(&dimensions_bytes);
     //写入矩阵名称
-    let name_bytes = write_array_name_subelement(&array.name, endianness)?;
+    let name_bytes = write_array_name_subelement(name, endianness)?;
     bytes.put_slice(&name_bytes);
     //写入矩阵数据
     let numeric_bytes = write_numeric_subelement(array, endianness)?;
     bytes.put_slice(&numeric_bytes);
     Ok(bytes)
 }
+//写入逻辑数组,与MATLAB一致使用miUINT8存储
+fn write_logical_matrix_subelements(
+    name: &str,
+    array: &LogicalArray,
+    endianness: nom::number::Endianness,
+) -> Result<BytesMut, MatError> {
+    let mut bytes = BytesMut::new();
+    let dimensions_bytes = write_dimensions_array_subelement(&array.size, endianness)?;
+    bytes.put_slice(&dimensions_bytes);
+    let name_bytes = write_array_name_subelement(name, endianness)?;
+    bytes.put_slice(&name_bytes);
+    let (logical_tag_bytes, padding) =
+        write_data_element_tag(DataType::UInt8, array.data.len() as u32, endianness)?;
+    bytes.put_slice(&logical_tag_bytes);
+    for &v in array.data.iter() {
+        bytes.put_u8(v as u8);
+    }
+    bytes.put_bytes(0, padding as usize);
+    Ok(bytes)
+}
+//写入字符矩阵,与MATLAB一致使用miUINT16存储UTF-16编码
+fn write_char_matrix_subelements(
+    name: &str,
+    array: &CharArray,
+    endianness: nom::number::Endianness,
+) -> Result<BytesMut, MatError> {
+    let mut bytes = BytesMut::new();
+    let dimensions_bytes = write_dimensions_array_subelement(&array.size, endianness)?;
+    bytes.put_slice(&dimensions_bytes);
+    let name_bytes = write_array_name_subelement(name, endianness)?;
+    bytes.put_slice(&name_bytes);
+    let (char_tag_bytes, padding) =
+        write_data_element_tag(DataType::UInt16, array.data.len() as u32 * 2, endianness)?;
+    bytes.put_slice(&char_tag_bytes);
+    for v in array.data.iter() {
+        if endianness == nom::number::Endianness::Big {
+            bytes.put_u16(*v);
+        } else {
+            bytes.put_u16_le(*v);
+        }
+    }
+    bytes.put_bytes(0, padding as usize);
+    Ok(bytes)
+}
 // pub fn is_packable(num_bytes: u32) -> bool {
 //     if num_bytes < 4 {
 //         true
@@ -184,18 +262,123 @@ fn write_numeric_matrix_subelements(
 //         false
 //     }
 // }
+//写入cell,每个元素都是一个名称为空的miMATRIX
+fn write_cell_matrix_subelements(
+    name: &str,
+    array: &CellArray,
+    endianness: nom::number::Endianness,
+) -> Result<BytesMut, MatError> {
+    let mut bytes = BytesMut::new();
+    let dimensions_bytes = write_dimensions_array_subelement(&array.size, endianness)?;
+    bytes.put_slice(&dimensions_bytes);
+    let name_bytes = write_array_name_subelement(name, endianness)?;
+    bytes.put_slice(&name_bytes);
+    for value in array.data.iter() {
+        let element_bytes = write_matrix_data_element("", value, endianness)?;
+        bytes.put_slice(&element_bytes);
+    }
+    Ok(bytes)
+}
+//写入结构体:字段名长度、按该长度补0的字段名,再按元素依次写入各字段
+fn write_struct_matrix_subelements(
+    name: &str,
+    array: &StructArray,
+    endianness: nom::number::Endianness,
+) -> Result<BytesMut, MatError> {
+    let mut bytes = BytesMut::new();
+    let dimensions_bytes = write_dimensions_array_subelement(&array.size, endianness)?;
+    bytes.put_slice(&dimensions_bytes);
+    let name_bytes = write_array_name_subelement(name, endianness)?;
+    bytes.put_slice(&name_bytes);
+    let field_name_length = array
+        .field_names
+        .iter()
+        .map(|field| field.len() + 1)
+        .max()
+        .unwrap_or(1);
+    let (length_tag_bytes, padding) = write_data_element_tag(DataType::Int32, 4, endianness)?;
+    bytes.put_slice(&length_tag_bytes);
+    if endianness == nom::number::Endianness::Big {
+        bytes.put_i32(field_name_length as i32);
+    } else {
+        bytes.put_i32_le(field_name_length as i32);
+    }
+    bytes.put_bytes(0, padding as usize);
+    let names_size = field_name_length * array.field_names.len();
+    let (names_tag_bytes, padding) =
+        write_data_element_tag(DataType::Int8, names_size as u32, endianness)?;
+    bytes.put_slice(&names_tag_bytes);
+    for field in array.field_names.iter() {
+        bytes.put_slice(field.as_bytes());
+        bytes.put_bytes(0, field_name_length - field.len());
+    }
+    bytes.put_bytes(0, padding as usize);
+    for value in array.data.iter() {
+        let field_bytes = write_matrix_data_element("", value, endianness)?;
+        bytes.put_slice(&field_bytes);
+    }
+    Ok(bytes)
+}
+//写入稀疏矩阵:ir、jc为miINT32,数值为miDOUBLE,逻辑稀疏矩阵的数值为miUINT8
+fn write_sparse_matrix_subelements(
+    name: &str,
+    array: &SparseArray,
+    endianness: nom::number::Endianness,
+) -> Result<BytesMut, MatError> {
+    let mut bytes = BytesMut::new();
+    let dimensions_bytes = write_dimensions_array_subelement(&array.size, endianness)?;
+    bytes.put_slice(&dimensions_bytes);
+    let name_bytes = write_array_name_subelement(name, endianness)?;
+    bytes.put_slice(&name_bytes);
+    for index in [array.ir(), array.jc()] {
+        let (index_tag_bytes, padding) =
+            write_data_element_tag(DataType::Int32, index.len() as u32 * 4, endianness)?;
+        bytes.put_slice(&index_tag_bytes);
+        for &i in index {
+            if endianness == nom::number::Endianness::Big {
+                bytes.put_i32(i as i32);
+            } else {
+                bytes.put_i32_le(i as i32);
+            }
+        }
+        bytes.put_bytes(0, padding as usize);
+    }
+    if array.is_logical() {
+        let (real_tag_bytes, padding) =
+            write_data_element_tag(DataType::UInt8, array.nnz() as u32, endianness)?;
+        bytes.put_slice(&real_tag_bytes);
+        for &v in array.real() {
+            bytes.put_u8((v != 0.0) as u8);
+        }
+        bytes.put_bytes(0, padding as usize);
+        return Ok(bytes);
+    }
+    for values in [Some(array.real()), array.imag()].into_iter().flatten() {
+        let (values_tag_bytes, padding) =
+            write_data_element_tag(DataType::Double, values.len() as u32 * 8, endianness)?;
+        bytes.put_slice(&values_tag_bytes);
+        for &v in values {
+            if endianness == nom::number::Endianness::Big {
+                bytes.put_f64(v);
+            } else {
+                bytes.put_f64_le(v);
+            }
+        }
+        bytes.put_bytes(0, padding as usize);
+    }
+    Ok(bytes)
+}
 //写入矩阵维数
 fn write_dimensions_array_subelement(
-    array: &Array,
+    size: &[usize],
     endianness: nom::number::Endianness,
 ) -> Result<BytesMut, MatError> {
-    let data_type = array.array_flags.class.numeric_data_type().unwrap();
     let mut bytes = BytesMut::new();
-    let dimension_size = array.size.len() * 4;
+    let dimension_size = size.len() * 4;
     let (dimension_tag_byte, padding) =
         write_data_element_tag(DataType::Int32, dimension_size as u32, endianness)?;
     bytes.put_slice(&dimension_tag_byte);
-    for size in array.size.iter() {
+    for size in size.iter() {
         let dimension = *size as i32;
         if endianness == nom::number::Endianness::Big {
             bytes.put_i32(dimension);
@@ -216,7 +399,7 @@ fn write_array_name_subelement(
     let (name_tag_bytes, padding) =
         write_data_element_tag(DataType::Int8, name_size as u32, endianness)?;
     bytes.put_slice(&name_tag_bytes);
-    bytes.put_slice(name.as_bytes());
+    bytes.put_slice(&name.as_bytes()[..name_size]);
     bytes.put_bytes(0, padding as usize);
     Ok(bytes)
 }
@@ -239,7 +422,7 @@ fn write_numeric_subelement(
     bytes.put_slice(&real_bytes);
     bytes.put_bytes(0, padding as usize);
     //处理虚部
-    if imag_size > 0 {
+    if array.data.is_complex() {
         let (imag_data_tag, padding) =
             write_data_element_tag(mat_type, imag_size as u32, endianness)?;
         bytes.put_slice(&imag_data_tag);
@@ -0,0 +1,11 @@
+% 生成 mcos.mat, 供 mcos::tests::read_matlab_saved_objects 使用。
+% 需要 R2019b 或更新的版本, 在本目录下运行: make_mcos
+s = ["ab", missing; "", char(181) + "x"];
+d = datetime(2020, 1, 2, 3, 4, 5, 'TimeZone', 'UTC') + days([0, 1]);
+d(3) = NaT;
+dur = seconds([1.5; 2]);
+t = table([1; 2], {'x'; 'y'}, 'VariableNames', {'a', 'b'}, 'RowNames', {'r1', 'r2'});
+tt = timetable(seconds([1; 2]), [5; 6], 'VariableNames', {'v'});
+c = categorical({'lo', 'hi', 'lo', ''}, {'lo', 'hi'}, 'Ordinal', true);
+plain = [1, 2, 3];
+save('mcos.mat', 's', 'd', 'dur', 't', 'tt', 'c', 'plain', '-v7');